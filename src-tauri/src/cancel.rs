@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Parse was cancelled")]
+pub struct Cancelled;
+
+/// Shared flag a running parse polls between files. The default token is
+/// never cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Tokens of the parses currently running, keyed by parse id. Kept in Tauri's
+/// managed state so `cancel_parse` can reach them.
+#[derive(Debug, Default)]
+pub struct ParseRegistry {
+    tokens: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl ParseRegistry {
+    pub fn register(&self, parse_id: &str) -> CancelToken {
+        let token = CancelToken::default();
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(parse_id.to_string(), token.clone());
+        token
+    }
+
+    pub fn remove(&self, parse_id: &str) {
+        self.tokens.lock().unwrap().remove(parse_id);
+    }
+
+    /// Returns false when no parse with that id is running.
+    pub fn cancel(&self, parse_id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(parse_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelling_reaches_every_clone_of_a_token() {
+        let token = CancelToken::default();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(clone.check().is_err());
+    }
+
+    #[test]
+    fn only_registered_parses_can_be_cancelled() {
+        let registry = ParseRegistry::default();
+        let token = registry.register("a");
+
+        assert!(!registry.cancel("b"));
+        assert!(registry.cancel("a"));
+        assert!(token.is_cancelled());
+
+        registry.remove("a");
+        assert!(!registry.cancel("a"));
+    }
+}
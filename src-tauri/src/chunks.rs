@@ -0,0 +1,366 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::output::{self, count_tokens, FileWriter};
+use crate::tokenizer::TokenizerKind;
+use crate::utils::ParseOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkBudget {
+    Tokens(u64),
+    Bytes(u64),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub index: usize,
+    pub file_name: String,
+    pub files_count: usize,
+    pub first_path: String,
+    pub last_path: String,
+    pub size: u64,
+    pub tokens: u64,
+}
+
+pub fn chunk_file_name(index: usize) -> String {
+    format!("content-{:03}.txt", index)
+}
+
+struct OpenChunk {
+    file: BufWriter<File>,
+    info: ChunkInfo,
+    cost: u64,
+}
+
+/// Writes `content-001.txt`, `content-002.txt`, ... next to `content.txt`,
+/// each within the budget. A file is only split across chunks when it does
+/// not fit into an empty chunk on its own.
+pub struct ChunkWriter {
+    dir: PathBuf,
+    budget: ChunkBudget,
+    tokenizer: TokenizerKind,
+    writer: Box<dyn FileWriter>,
+    /// Index of the next document. Documents are numbered across all chunks,
+    /// and each part of a split file counts as a document of its own.
+    next_index: usize,
+    prologue: Vec<u8>,
+    epilogue: Vec<u8>,
+    current: Option<OpenChunk>,
+    chunks: Vec<ChunkInfo>,
+}
+
+impl ChunkWriter {
+    pub fn new(dir: &Path, budget: ChunkBudget, options: &ParseOptions) -> io::Result<Self> {
+        if matches!(budget, ChunkBudget::Tokens(0) | ChunkBudget::Bytes(0)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk budget must be greater than zero",
+            ));
+        }
+
+        let mut writer = output::writer_for(options);
+        let mut prologue = Vec::new();
+        writer.write_prologue(&mut prologue)?;
+        let mut epilogue = Vec::new();
+        writer.write_epilogue(&mut epilogue)?;
+
+        Ok(ChunkWriter {
+            dir: dir.to_path_buf(),
+            budget,
+            tokenizer: options.tokenizer,
+            writer,
+            next_index: 1,
+            prologue,
+            epilogue,
+            current: None,
+            chunks: Vec::new(),
+        })
+    }
+
+    /// `tokens` is the size of the file as already counted for `content.txt`,
+    /// which saves tokenizing every file twice.
+    pub fn write_file(
+        &mut self,
+        path: &str,
+        source: &Path,
+        content: &str,
+        tokens: u64,
+    ) -> io::Result<()> {
+        let block = self.render(path, source, content)?;
+        let cost = match self.budget {
+            ChunkBudget::Tokens(_) => tokens,
+            ChunkBudget::Bytes(_) => block.len() as u64,
+        };
+
+        if cost + self.framing_cost() <= self.limit() {
+            return self.push_block(&block, cost, tokens, path);
+        }
+
+        let parts = self.split_content(path, source, content)?;
+        let total = parts.len();
+        for (i, part) in parts.iter().enumerate() {
+            let part_path = format!("{} (part {}/{})", path, i + 1, total);
+            let block = self.render(&part_path, source, part)?;
+            let tokens = count_tokens(self.tokenizer, &block);
+            let cost = self.cost(&block);
+            self.push_block(&block, cost, tokens, path)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<Vec<ChunkInfo>> {
+        self.close_current()?;
+        Ok(self.chunks)
+    }
+
+    fn limit(&self) -> u64 {
+        match self.budget {
+            ChunkBudget::Tokens(limit) | ChunkBudget::Bytes(limit) => limit,
+        }
+    }
+
+    fn cost(&self, rendered: &[u8]) -> u64 {
+        match self.budget {
+            ChunkBudget::Bytes(_) => rendered.len() as u64,
+            ChunkBudget::Tokens(_) => count_tokens(self.tokenizer, rendered),
+        }
+    }
+
+    fn framing_cost(&self) -> u64 {
+        self.cost(&self.prologue) + self.cost(&self.epilogue)
+    }
+
+    fn render(&mut self, path: &str, source: &Path, content: &str) -> io::Result<Vec<u8>> {
+        let mut block = Vec::new();
+        self.writer
+            .write_file(&mut block, self.next_index, path, source, content)?;
+        Ok(block)
+    }
+
+    /// Groups whole lines into parts that each fit into an empty chunk
+    /// together with their header. Lines are measured before the writer
+    /// escapes them, so a part that still ends up too large once rendered is
+    /// halved until it fits. A single line longer than the budget becomes a
+    /// part of its own.
+    fn split_content(
+        &mut self,
+        path: &str,
+        source: &Path,
+        content: &str,
+    ) -> io::Result<Vec<String>> {
+        let header_path = format!("{} (part 000/000)", path);
+        let header = self.render(&header_path, source, "")?;
+        let available = self
+            .limit()
+            .saturating_sub(self.framing_cost() + self.cost(&header))
+            .max(1);
+
+        let mut estimated = Vec::new();
+        let mut part = String::new();
+        let mut part_cost = 0;
+
+        for line in content.split_inclusive('\n') {
+            let line_cost = self.cost(line.as_bytes());
+            if !part.is_empty() && part_cost + line_cost > available {
+                estimated.push(std::mem::take(&mut part));
+                part_cost = 0;
+            }
+            part.push_str(line);
+            part_cost += line_cost;
+        }
+        if !part.is_empty() || estimated.is_empty() {
+            estimated.push(part);
+        }
+
+        let mut parts = Vec::new();
+        for part in estimated {
+            self.fit_part(&header_path, source, part, &mut parts)?;
+        }
+        Ok(parts)
+    }
+
+    fn fit_part(
+        &mut self,
+        header_path: &str,
+        source: &Path,
+        part: String,
+        parts: &mut Vec<String>,
+    ) -> io::Result<()> {
+        let lines: Vec<&str> = part.split_inclusive('\n').collect();
+        let block = self.render(header_path, source, &part)?;
+        if lines.len() <= 1 || self.cost(&block) + self.framing_cost() <= self.limit() {
+            parts.push(part);
+            return Ok(());
+        }
+
+        let (head, tail) = lines.split_at(lines.len() / 2);
+        let (head, tail) = (head.concat(), tail.concat());
+        self.fit_part(header_path, source, head, parts)?;
+        self.fit_part(header_path, source, tail, parts)
+    }
+
+    fn push_block(&mut self, block: &[u8], cost: u64, tokens: u64, path: &str) -> io::Result<()> {
+        let limit = self.limit();
+        let framing_cost = self.framing_cost();
+
+        if let Some(current) = &self.current {
+            if current.cost + cost + framing_cost > limit {
+                self.close_current()?;
+            }
+        }
+
+        if self.current.is_none() {
+            self.open_chunk(path)?;
+        }
+
+        self.next_index += 1;
+        if let Some(current) = &mut self.current {
+            current.file.write_all(block)?;
+            current.cost += cost;
+            current.info.size += block.len() as u64;
+            current.info.tokens += tokens;
+            if current.info.last_path != path || current.info.files_count == 0 {
+                current.info.files_count += 1;
+            }
+            current.info.last_path = path.to_string();
+        }
+        Ok(())
+    }
+
+    fn open_chunk(&mut self, first_path: &str) -> io::Result<()> {
+        let index = self.chunks.len() + 1;
+        let file_name = chunk_file_name(index);
+        let mut file = BufWriter::new(File::create(self.dir.join(&file_name))?);
+        file.write_all(&self.prologue)?;
+
+        self.current = Some(OpenChunk {
+            file,
+            info: ChunkInfo {
+                index,
+                file_name,
+                files_count: 0,
+                first_path: first_path.to_string(),
+                last_path: String::new(),
+                size: self.prologue.len() as u64,
+                tokens: count_tokens(self.tokenizer, &self.prologue),
+            },
+            cost: 0,
+        });
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> io::Result<()> {
+        let Some(mut current) = self.current.take() else {
+            return Ok(());
+        };
+        current.file.write_all(&self.epilogue)?;
+        current.file.flush()?;
+
+        current.info.size += self.epilogue.len() as u64;
+        current.info.tokens += count_tokens(self.tokenizer, &self.epilogue);
+        self.chunks.push(current.info);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputFormat;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_chunks(options: &ParseOptions, files: &[(&str, &str)]) -> (TempDir, Vec<ChunkInfo>) {
+        let dir = TempDir::new().unwrap();
+        let budget = options.chunk_budget.unwrap();
+        let mut chunks = ChunkWriter::new(dir.path(), budget, options).unwrap();
+        for (path, content) in files {
+            chunks
+                .write_file(path, Path::new(path), content, 0)
+                .unwrap();
+        }
+        let infos = chunks.finish().unwrap();
+        (dir, infos)
+    }
+
+    #[test]
+    fn files_are_grouped_within_the_budget() {
+        let options = ParseOptions {
+            chunk_budget: Some(ChunkBudget::Bytes(50)),
+            ..ParseOptions::default()
+        };
+        let files = [("a.txt", "aaaa"), ("b.txt", "bbbb"), ("c.txt", "cccc")];
+        let (dir, infos) = write_chunks(&options, &files);
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].files_count, 2);
+        assert_eq!(
+            (infos[1].first_path.as_str(), infos[1].files_count),
+            ("c.txt", 1)
+        );
+        for info in &infos {
+            let size = fs::metadata(dir.path().join(&info.file_name))
+                .unwrap()
+                .len();
+            assert_eq!(size, info.size);
+            assert!(size <= 50);
+        }
+    }
+
+    #[test]
+    fn oversized_files_are_split_into_parts() {
+        let options = ParseOptions {
+            chunk_budget: Some(ChunkBudget::Bytes(50)),
+            ..ParseOptions::default()
+        };
+        let content = "line one\nline two\nline three\nline four\n";
+        let (dir, infos) = write_chunks(&options, &[("big.txt", content)]);
+
+        assert!(infos.len() > 1);
+        let mut rejoined = String::new();
+        for info in &infos {
+            let chunk = fs::read_to_string(dir.path().join(&info.file_name)).unwrap();
+            let (header, body) = chunk.split_once('\n').unwrap();
+            assert!(header.contains(&format!("big.txt (part {}/{})", info.index, infos.len())));
+            rejoined.push_str(body.strip_suffix('\n').unwrap());
+        }
+        assert_eq!(rejoined, content);
+    }
+
+    #[test]
+    fn empty_budgets_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let options = ParseOptions::default();
+        assert!(ChunkWriter::new(dir.path(), ChunkBudget::Tokens(0), &options).is_err());
+        assert!(ChunkWriter::new(dir.path(), ChunkBudget::Bytes(0), &options).is_err());
+    }
+
+    #[test]
+    fn xml_document_indices_continue_across_chunks() {
+        let options = ParseOptions {
+            format: OutputFormat::Xml,
+            chunk_budget: Some(ChunkBudget::Bytes(150)),
+            ..ParseOptions::default()
+        };
+        let big = "line one\nline two\nline three\nline four\n";
+        let files = [("a.txt", "a"), ("big.txt", big), ("c.txt", "c")];
+        let (dir, infos) = write_chunks(&options, &files);
+
+        assert!(infos.len() > 3);
+        let mut indices = Vec::new();
+        for info in &infos {
+            let chunk = fs::read_to_string(dir.path().join(&info.file_name)).unwrap();
+            for line in chunk.lines() {
+                if let Some(index) = line.strip_prefix("<document index=\"") {
+                    indices.push(index.trim_end_matches("\">").parse::<usize>().unwrap());
+                }
+            }
+        }
+        assert_eq!(indices, (1..=indices.len()).collect::<Vec<_>>());
+    }
+}
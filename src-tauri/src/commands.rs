@@ -1,10 +1,19 @@
+use crate::cache::{self, CachedRepo};
+use crate::cancel::ParseRegistry;
+use crate::credentials::{self, Credential, CredentialInfo};
 use crate::error::CommandError;
-use crate::utils::{self, ParseMetadata, ParsedPath, PARSED_FILES_DIR};
+use crate::git;
+use crate::profiles::{self, ParseProfile};
+use crate::progress::TauriEventSink;
+use crate::report::ParseReport;
+use crate::utils::{self, ParseMetadata, ParseOptions, ParsedPath, PARSED_FILES_DIR};
+use crate::walk::PathFilter;
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use futures::future::join_all;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
 use tokio::fs as tokio_fs;
 
 #[derive(serde::Serialize)]
@@ -16,6 +25,7 @@ pub struct ParsedFileListItem {
     pub file_size: u64,
     pub files_count: usize,
     pub total_size: u64,
+    pub total_tokens: u64,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>,
 }
@@ -28,21 +38,43 @@ pub struct ParsedFileListItem {
 pub async fn parse(
     paths: Vec<String>,
     remote_url: Option<String>,
+    options: Option<ParseOptions>,
+    profile_id: Option<String>,
     app: tauri::AppHandle,
 ) -> Result<ParseMetadata, CommandError> {
-    let result =
-        tauri::async_runtime::spawn_blocking(move || utils::parse_files(paths, app, remote_url))
-            .await
-            .map_err(|e| CommandError::from(anyhow::anyhow!("Thread join error: {}", e)))?
-            .map_err(CommandError::from)?;
+    let options = profiles::resolve_options(options, profile_id)?;
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let sink = TauriEventSink::new(app.clone());
+        let registry = app.state::<ParseRegistry>();
+        utils::parse_files(paths, remote_url, options, &sink, &registry)
+    })
+    .await
+    .map_err(|e| CommandError::from(anyhow::anyhow!("Thread join error: {}", e)))?
+    .map_err(CommandError::from)?;
 
     Ok(result)
 }
 
 #[tauri::command]
-pub async fn parse_repository(url: String) -> Result<String, CommandError> {
+pub fn cancel_parse(
+    parse_id: String,
+    registry: tauri::State<'_, ParseRegistry>,
+) -> Result<(), CommandError> {
+    if !registry.cancel(&parse_id) {
+        return Err(anyhow::anyhow!("No running parse with id {}", parse_id).into());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn parse_repository(
+    url: String,
+    git_ref: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String, CommandError> {
     let result = tauri::async_runtime::spawn_blocking(move || -> anyhow::Result<String> {
-        let path = utils::clone_git_repo(&url)?;
+        let sink = TauriEventSink::new(app);
+        let path = git::clone_git_repo(&url, git_ref.as_deref(), &sink)?;
         Ok(path.to_string_lossy().to_string())
     })
     .await
@@ -52,21 +84,100 @@ pub async fn parse_repository(url: String) -> Result<String, CommandError> {
     Ok(result)
 }
 
+// /////////////////////////////////////////////////////////////////////////////
+// Git Credentials
+// /////////////////////////////////////////////////////////////////////////////
+
+#[tauri::command]
+pub fn list_credentials() -> Result<Vec<CredentialInfo>, CommandError> {
+    Ok(credentials::list_credentials()?)
+}
+
+#[tauri::command]
+pub fn set_credential(
+    host: String,
+    credential: Credential,
+) -> Result<CredentialInfo, CommandError> {
+    Ok(credentials::set_credential(&host, credential)?)
+}
+
+#[tauri::command]
+pub fn delete_credential(host: String) -> Result<(), CommandError> {
+    credentials::delete_credential(&host)?;
+    Ok(())
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Repository Cache
+// /////////////////////////////////////////////////////////////////////////////
+
+#[tauri::command]
+pub fn list_repo_cache() -> Result<Vec<CachedRepo>, CommandError> {
+    Ok(cache::list_cache()?)
+}
+
+#[tauri::command]
+pub async fn clear_repo_cache() -> Result<(), CommandError> {
+    tauri::async_runtime::spawn_blocking(cache::clear_cache)
+        .await
+        .map_err(|e| CommandError::from(anyhow::anyhow!("Thread join error: {}", e)))?
+        .map_err(CommandError::from)
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Parse Profiles
+// /////////////////////////////////////////////////////////////////////////////
+
+#[tauri::command]
+pub fn list_profiles() -> Result<Vec<ParseProfile>, CommandError> {
+    Ok(profiles::list_profiles()?)
+}
+
+#[tauri::command]
+pub fn create_profile(name: String, options: ParseOptions) -> Result<ParseProfile, CommandError> {
+    Ok(profiles::create_profile(name, options)?)
+}
+
+#[tauri::command]
+pub fn update_profile(
+    id: String,
+    name: Option<String>,
+    options: Option<ParseOptions>,
+) -> Result<ParseProfile, CommandError> {
+    Ok(profiles::update_profile(&id, name, options)?)
+}
+
+#[tauri::command]
+pub fn delete_profile(id: String) -> Result<(), CommandError> {
+    profiles::delete_profile(&id)?;
+    Ok(())
+}
+
 // /////////////////////////////////////////////////////////////////////////////
 // File System Preview & Expansion
 // /////////////////////////////////////////////////////////////////////////////
 
+/// Preview of `paths` as a parse with `options` would see them.
 #[tauri::command]
-pub async fn get_preview_tree(paths: Vec<String>) -> Result<Vec<ParsedPath>, CommandError> {
+pub async fn get_preview_tree(
+    paths: Vec<String>,
+    options: Option<ParseOptions>,
+) -> Result<Vec<ParsedPath>, CommandError> {
     let mut result = Vec::new();
+    let options = options.unwrap_or_default();
+    let filter = PathFilter::new(&options.include, &options.exclude)?;
+    let order = options.sort_order;
+    let roots = utils::resolve_parse_roots(&paths);
 
     let tasks: Vec<_> = paths
         .into_iter()
-        .map(|input| {
+        .zip(roots)
+        .map(|(input, root)| {
+            let filter = filter.with_root(&root);
             tokio::task::spawn_blocking(move || {
                 let path = PathBuf::from(input);
                 if path.exists() {
-                    return Some(utils::build_file_tree_shallow(&path));
+                    return Some(utils::build_file_tree_shallow(&path, &filter, order));
                 }
                 None
             })
@@ -84,43 +195,29 @@ pub async fn get_preview_tree(paths: Vec<String>) -> Result<Vec<ParsedPath>, Com
     Ok(result)
 }
 
+/// Children of the previewed folder `path`. Include/exclude patterns in
+/// `options` are matched relative to `root`, the directory the preview
+/// resolved them against, or to `path` itself when it is not given.
 #[tauri::command]
-pub async fn expand_folder(path: String) -> Result<Vec<ParsedPath>, CommandError> {
+pub async fn expand_folder(
+    path: String,
+    root: Option<String>,
+    options: Option<ParseOptions>,
+) -> Result<Vec<ParsedPath>, CommandError> {
+    let options = options.unwrap_or_default();
+    let filter = PathFilter::new(&options.include, &options.exclude)?;
+    let order = options.sort_order;
     let result = tauri::async_runtime::spawn_blocking(move || {
         let path_buf = PathBuf::from(path);
+        let filter = filter.with_root(root.as_deref().map_or(&path_buf, Path::new));
 
-        let mut children = Vec::new();
-        if path_buf.is_dir() {
-            if let Ok(entries) = fs::read_dir(&path_buf) {
-                for entry in entries.flatten() {
-                    let child_path = entry.path();
-                    if child_path.is_symlink() {
-                        continue;
-                    }
-
-                    if let Ok(_node) = utils::build_file_tree_shallow(&child_path) {
-                        if let Ok(node) = utils::create_shallow_node(&child_path) {
-                            children.push(node);
-                        }
-                    }
-                }
-            }
+        if !path_buf.is_dir() {
+            return Ok(Vec::new());
         }
-
-        children.sort_by(|a, b| {
-            let is_a_dir = matches!(a, ParsedPath::Directory { .. });
-            let is_b_dir = matches!(b, ParsedPath::Directory { .. });
-            match (is_a_dir, is_b_dir) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.path().cmp(b.path()),
-            }
-        });
-
-        children
+        utils::shallow_children(&path_buf, &filter, order)
     })
     .await
-    .map_err(|e| CommandError::from(anyhow::anyhow!("Thread join error: {}", e)))?;
+    .map_err(|e| CommandError::from(anyhow::anyhow!("Thread join error: {}", e)))??;
 
     Ok(result)
 }
@@ -201,6 +298,7 @@ async fn load_list_item(path: PathBuf) -> Result<ParsedFileListItem, anyhow::Err
         file_size,
         files_count: metadata.files_count,
         total_size: metadata.total_size,
+        total_tokens: metadata.total_tokens,
         created_at: metadata.created_at,
         updated_at: metadata.updated_at,
     })
@@ -218,6 +316,25 @@ pub fn get_file_metadata(dir_name: String) -> Result<ParseMetadata, CommandError
     Ok(utils::load_metadata(&parse_dir)?)
 }
 
+#[tauri::command]
+pub fn get_parse_report(dir_name: String) -> Result<ParseReport, CommandError> {
+    let parse_dir = utils::get_parse_dir(&dir_name)?;
+    Ok(utils::load_report(&parse_dir)?)
+}
+
+#[tauri::command]
+pub fn get_chunk_content(dir_name: String, index: usize) -> Result<String, CommandError> {
+    let parse_dir = utils::get_parse_dir(&dir_name)?;
+    Ok(utils::load_chunk(&parse_dir, index)?)
+}
+
+#[tauri::command]
+pub fn export_parse_jsonl(dir_name: String) -> Result<String, CommandError> {
+    let parse_dir = utils::get_parse_dir(&dir_name)?;
+    let jsonl_path = utils::export_jsonl(&parse_dir)?;
+    Ok(jsonl_path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub fn update_file(dir_name: String, content: String) -> Result<(), CommandError> {
     let parse_dir = utils::get_parse_dir(&dir_name)?;
@@ -282,6 +399,15 @@ pub fn open_in_default_editor(dir_name: String) -> Result<(), CommandError> {
     Ok(())
 }
 
+#[tauri::command]
+pub fn open_chunk_in_default_editor(dir_name: String, index: usize) -> Result<(), CommandError> {
+    let parse_dir = utils::get_parse_dir(&dir_name)?;
+    utils::open_with_default_app(utils::OpenAction::OpenFile(utils::get_chunk_path(
+        &parse_dir, index,
+    )?))?;
+    Ok(())
+}
+
 #[tauri::command]
 pub fn open_in_folder(dir_name: String) -> Result<(), CommandError> {
     let parse_dir = utils::get_parse_dir(&dir_name)?;
@@ -0,0 +1,374 @@
+use anyhow::{self, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::{self, StoreLock};
+
+pub const CREDENTIALS_FILENAME: &str = "credentials.enc";
+/// Name of the OS keychain entry holding the credential file's key, under
+/// the app's name as the service.
+pub const CREDENTIALS_KEY_ENTRY: &str = "credentials-key";
+
+const NONCE_SIZE: usize = 12;
+
+static CREDENTIALS_LOCK: StoreLock = StoreLock::new();
+
+/// How to authenticate against a git host. Deliberately not `Debug`, so
+/// secrets cannot end up in logs.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Credential {
+    /// Personal access token, sent as the password of HTTPS remotes.
+    Token {
+        /// Some hosts want the account name with the token. Defaults to
+        /// `git`, which GitHub and GitLab accept for any token.
+        #[serde(default)]
+        username: Option<String>,
+        token: String,
+    },
+    /// Key pair for SSH remotes.
+    SshKey {
+        private_key_path: String,
+        #[serde(default)]
+        public_key_path: Option<String>,
+        #[serde(default)]
+        passphrase: Option<String>,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HostCredential {
+    pub host: String,
+    pub credential: Credential,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+/// What the frontend gets to see of a stored credential: never the secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialInfo {
+    pub host: String,
+    pub kind: String,
+    pub username: Option<String>,
+    pub private_key_path: Option<String>,
+    pub updated_at: DateTime<Local>,
+}
+
+impl From<&HostCredential> for CredentialInfo {
+    fn from(stored: &HostCredential) -> Self {
+        let (kind, username, private_key_path) = match &stored.credential {
+            Credential::Token { username, .. } => ("token", username.clone(), None),
+            Credential::SshKey {
+                private_key_path, ..
+            } => ("ssh_key", None, Some(private_key_path.clone())),
+        };
+        CredentialInfo {
+            host: stored.host.clone(),
+            kind: kind.to_string(),
+            username,
+            private_key_path,
+            updated_at: stored.updated_at,
+        }
+    }
+}
+
+pub fn get_credentials_path() -> Result<PathBuf> {
+    Ok(utils::get_app_dir()?.join(CREDENTIALS_FILENAME))
+}
+
+pub fn list_credentials() -> Result<Vec<CredentialInfo>> {
+    let _guard = CREDENTIALS_LOCK.lock();
+    Ok(load_credentials()?
+        .iter()
+        .map(CredentialInfo::from)
+        .collect())
+}
+
+/// The credential stored for the host `url` points at, if any. A credential
+/// file that cannot be read is logged and treated as empty, so public
+/// repositories can still be cloned.
+pub fn credential_for_url(url: &str) -> Option<Credential> {
+    let host = host_from_url(url)?;
+    let _guard = CREDENTIALS_LOCK.lock();
+    let credentials = match load_credentials() {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            eprintln!("Failed to load credentials, continuing without: {}", e);
+            return None;
+        }
+    };
+    credentials
+        .into_iter()
+        .find(|stored| stored.host == host)
+        .map(|stored| stored.credential)
+}
+
+/// Stores `credential` for `host`, replacing any credential it had.
+pub fn set_credential(host: &str, credential: Credential) -> Result<CredentialInfo> {
+    let _guard = CREDENTIALS_LOCK.lock();
+    let host = normalize_host(host)?;
+    validate_credential(&credential)?;
+
+    let mut credentials = load_credentials()?;
+    let now = Local::now();
+    let created_at = credentials
+        .iter()
+        .find(|stored| stored.host == host)
+        .map_or(now, |stored| stored.created_at);
+    credentials.retain(|stored| stored.host != host);
+
+    let stored = HostCredential {
+        host,
+        credential,
+        created_at,
+        updated_at: now,
+    };
+    let info = CredentialInfo::from(&stored);
+    credentials.push(stored);
+    save_credentials(&credentials)?;
+
+    Ok(info)
+}
+
+pub fn delete_credential(host: &str) -> Result<()> {
+    let _guard = CREDENTIALS_LOCK.lock();
+    let host = normalize_host(host)?;
+
+    let mut credentials = load_credentials()?;
+    let count = credentials.len();
+    credentials.retain(|stored| stored.host != host);
+    if credentials.len() == count {
+        return Err(anyhow::anyhow!("No credential stored for {}", host));
+    }
+    save_credentials(&credentials)
+}
+
+/// Host, and port if there is one, of an HTTPS, SSH or scp-like
+/// (`git@host:path`) URL, lowercased and without any user info.
+pub fn host_from_url(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        None => url.split_once(':')?.0,
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// Accepts a bare host as well as a URL on it.
+fn normalize_host(host: &str) -> Result<String> {
+    let host = host.trim();
+    let normalized = if host.contains("://") || host.contains('@') {
+        host_from_url(host)
+    } else {
+        let host = host.trim_end_matches('/');
+        (!host.is_empty()).then(|| host.to_ascii_lowercase())
+    };
+    normalized.ok_or_else(|| anyhow::anyhow!("Invalid host: {}", host))
+}
+
+fn validate_credential(credential: &Credential) -> Result<()> {
+    match credential {
+        Credential::Token { token, .. } if token.trim().is_empty() => {
+            Err(anyhow::anyhow!("Token cannot be empty"))
+        }
+        Credential::SshKey {
+            private_key_path, ..
+        } if !Path::new(private_key_path).is_file() => {
+            Err(anyhow::anyhow!("SSH key not found: {}", private_key_path))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn load_credentials() -> Result<Vec<HostCredential>> {
+    let path = get_credentials_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let bytes = fs::read(&path)?;
+    if bytes.len() < NONCE_SIZE {
+        return Err(anyhow::anyhow!("Credential file is corrupted"));
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE);
+    let nonce: [u8; NONCE_SIZE] = nonce.try_into()?;
+    let plaintext = cipher()?
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Credential file could not be decrypted"))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+fn save_credentials(credentials: &[HostCredential]) -> Result<()> {
+    let plaintext = serde_json::to_vec(credentials)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher()?
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt credentials"))?;
+
+    let mut contents = nonce.to_vec();
+    contents.extend_from_slice(&ciphertext);
+    utils::write_private(&get_credentials_path()?, &contents)
+}
+
+/// Cipher for the credential file. Its key is generated on first use and
+/// kept in the OS keychain (the macOS Keychain, the Windows Credential
+/// Manager or the Secret Service on Linux), never on disk, so the app dir
+/// alone does not give the secrets away.
+fn cipher() -> Result<ChaCha20Poly1305> {
+    if let Some(key) = read_key()? {
+        let key: [u8; 32] = key
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Credential key is corrupted"))?;
+        return Ok(ChaCha20Poly1305::new(&Key::from(key)));
+    }
+
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    write_key(&key)?;
+    Ok(ChaCha20Poly1305::new(&key))
+}
+
+#[cfg(not(test))]
+fn read_key() -> Result<Option<Vec<u8>>> {
+    match key_entry()?.get_secret() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keychain_error(e)),
+    }
+}
+
+#[cfg(not(test))]
+fn write_key(key: &[u8]) -> Result<()> {
+    key_entry()?.set_secret(key).map_err(keychain_error)
+}
+
+#[cfg(not(test))]
+fn key_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(utils::APP_NAME, CREDENTIALS_KEY_ENTRY).map_err(keychain_error)
+}
+
+#[cfg(not(test))]
+fn keychain_error(e: keyring::Error) -> anyhow::Error {
+    anyhow::anyhow!("Could not access the OS keychain: {}", e)
+}
+
+// Tests keep the key in their temporary app dir instead of the user's
+// keychain.
+#[cfg(test)]
+fn read_key() -> Result<Option<Vec<u8>>> {
+    let path = utils::get_app_dir()?.join(CREDENTIALS_KEY_ENTRY);
+    Ok(path.exists().then(|| fs::read(path)).transpose()?)
+}
+
+#[cfg(test)]
+fn write_key(key: &[u8]) -> Result<()> {
+    Ok(fs::write(
+        utils::get_app_dir()?.join(CREDENTIALS_KEY_ENTRY),
+        key,
+    )?)
+}
+
+/// Creates a file only the current user can read, where the platform
+/// supports it.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TestAppDir;
+
+    fn token(token: &str) -> Credential {
+        Credential::Token {
+            username: None,
+            token: token.to_string(),
+        }
+    }
+
+    #[test]
+    fn credentials_round_trip_through_the_encrypted_file() {
+        let app_dir = TestAppDir::new();
+        set_credential("https://GitHub.com/owner/repo", token("secret-token")).unwrap();
+
+        let stored = fs::read(app_dir.dir.path().join(CREDENTIALS_FILENAME)).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("secret-token"));
+
+        let found = credential_for_url("git@github.com:owner/repo.git");
+        assert!(matches!(found, Some(Credential::Token { token, .. }) if token == "secret-token"));
+        assert!(credential_for_url("https://gitlab.com/a/b").is_none());
+
+        delete_credential("github.com").unwrap();
+        assert!(list_credentials().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tampered_credential_files_are_rejected() {
+        let app_dir = TestAppDir::new();
+        set_credential("github.com", token("secret-token")).unwrap();
+
+        let path = app_dir.dir.path().join(CREDENTIALS_FILENAME);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(list_credentials().is_err());
+
+        fs::write(&path, &bytes[..NONCE_SIZE - 1]).unwrap();
+        assert!(list_credentials().is_err());
+        // Clones go ahead without credentials rather than failing.
+        assert!(credential_for_url("https://github.com/owner/repo").is_none());
+    }
+
+    #[test]
+    fn hosts_come_from_https_ssh_and_scp_urls() {
+        assert_eq!(
+            host_from_url("https://user:pw@GitHub.com/o/r").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            host_from_url("ssh://git@git.example.com:2222/o/r").as_deref(),
+            Some("git.example.com:2222")
+        );
+        assert_eq!(
+            host_from_url("git@gitlab.com:group/repo.git").as_deref(),
+            Some("gitlab.com")
+        );
+        assert_eq!(host_from_url("/local/path"), None);
+        assert_eq!(host_from_url("https:///path"), None);
+    }
+
+    #[test]
+    fn credential_info_never_carries_secrets() {
+        let now = Local::now();
+        let stored = [
+            HostCredential {
+                host: "github.com".to_string(),
+                credential: Credential::Token {
+                    username: Some("me".to_string()),
+                    token: "secret-token".to_string(),
+                },
+                created_at: now,
+                updated_at: now,
+            },
+            HostCredential {
+                host: "gitlab.com".to_string(),
+                credential: Credential::SshKey {
+                    private_key_path: "/keys/id".to_string(),
+                    public_key_path: None,
+                    passphrase: Some("secret-passphrase".to_string()),
+                },
+                created_at: now,
+                updated_at: now,
+            },
+        ];
+
+        for stored in &stored {
+            let json = serde_json::to_string(&CredentialInfo::from(stored)).unwrap();
+            assert!(!json.contains("secret"), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use chardetng::EncodingDetector;
+use content_inspector::{inspect, ContentType};
+use encoding_rs::{Decoder, DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::io::{self, Read};
+
+/// How much of a file is looked at to guess its encoding when only part of
+/// it is read.
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+const READ_SIZE: usize = 64 * 1024;
+
+/// Guesses the encoding of `bytes`. A byte order mark wins, then valid UTF-8,
+/// and anything else is left to the charset detector. When `complete` is
+/// false, `bytes` is only the start of the file and may end in the middle of
+/// a character.
+pub fn detect(bytes: &[u8], complete: bool) -> io::Result<&'static Encoding> {
+    match inspect(bytes) {
+        ContentType::UTF_8_BOM => return Ok(UTF_8),
+        ContentType::UTF_16LE => return Ok(UTF_16LE),
+        ContentType::UTF_16BE => return Ok(UTF_16BE),
+        ContentType::UTF_32LE | ContentType::UTF_32BE => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "UTF-32 text is not supported",
+            ));
+        }
+        _ => {}
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => return Ok(UTF_8),
+        // Only a character cut off by the end of the sample
+        Err(e) if !complete && e.error_len().is_none() => return Ok(UTF_8),
+        Err(_) => {}
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, complete);
+    Ok(detector.guess(None, true))
+}
+
+/// Where a partial read of a file stops: after `max_lines` lines or
+/// `max_bytes` bytes of input, whichever comes first.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadLimit {
+    pub max_lines: usize,
+    pub max_bytes: u64,
+}
+
+/// Reads text into UTF-8, transcoding it from whatever encoding it was
+/// detected to be in. With a `limit`, reading stops at whichever of its
+/// bounds comes first, and text cut off by the byte bound is cut back to its
+/// last complete line. Bytes that are invalid in the detected encoding fail
+/// the read with `InvalidData` rather than being replaced.
+pub fn read_text(
+    mut reader: impl Read,
+    limit: Option<ReadLimit>,
+) -> io::Result<(String, &'static Encoding)> {
+    let Some(limit) = limit else {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let encoding = detect(&bytes, true)?;
+        return Ok((decode(encoding, &bytes)?, encoding));
+    };
+
+    let mut reader = reader.take(limit.max_bytes);
+    let mut sample = Vec::with_capacity(SAMPLE_SIZE);
+    (&mut reader)
+        .take(SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample)?;
+    let mut last = sample.len() < SAMPLE_SIZE;
+    // Once the byte bound is used up, the input may end in the middle of a
+    // character, which is left undecoded rather than treated as malformed.
+    let mut cut = reader.limit() == 0;
+    let encoding = detect(&sample, last && !cut)?;
+
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut text = String::new();
+    let mut lines = 0;
+    let mut buf = vec![0u8; READ_SIZE];
+    let mut chunk = &sample[..];
+
+    loop {
+        let start = text.len();
+        decode_chunk(&mut decoder, chunk, &mut text, last && !cut)?;
+
+        for (offset, _) in text[start..].match_indices('\n') {
+            lines += 1;
+            if lines == limit.max_lines {
+                text.truncate(start + offset + 1);
+                return Ok((text, encoding));
+            }
+        }
+        if last {
+            if cut {
+                if let Some(end) = text.rfind('\n') {
+                    text.truncate(end + 1);
+                }
+            }
+            return Ok((text, encoding));
+        }
+
+        let read = reader.read(&mut buf)?;
+        chunk = &buf[..read];
+        last = read == 0;
+        cut = reader.limit() == 0;
+    }
+}
+
+fn decode_chunk(
+    decoder: &mut Decoder,
+    mut src: &[u8],
+    text: &mut String,
+    last: bool,
+) -> io::Result<()> {
+    loop {
+        let needed = decoder
+            .max_utf8_buffer_length_without_replacement(src.len())
+            .unwrap_or(src.len() * 3 + 16);
+        text.reserve(needed);
+        let (result, read) = decoder.decode_to_string_without_replacement(src, text, last);
+        src = &src[read..];
+        match result {
+            DecoderResult::InputEmpty => return Ok(()),
+            DecoderResult::OutputFull => continue,
+            DecoderResult::Malformed(_, _) => return Err(invalid_data(decoder.encoding())),
+        }
+    }
+}
+
+fn decode(encoding: &'static Encoding, bytes: &[u8]) -> io::Result<String> {
+    let bytes = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, bom_len)) if bom_encoding == encoding => &bytes[bom_len..],
+        _ => bytes,
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| invalid_data(encoding))
+}
+
+fn invalid_data(encoding: &'static Encoding) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("stream did not contain valid {}", encoding.name()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    #[test]
+    fn byte_order_marks_win_and_are_removed() {
+        let (text, encoding) = read_text(&b"\xEF\xBB\xBFhello"[..], None).unwrap();
+        assert_eq!((text.as_str(), encoding), ("hello", UTF_8));
+
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("h\u{e9}".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let (text, encoding) = read_text(&utf16[..], None).unwrap();
+        assert_eq!((text.as_str(), encoding), ("h\u{e9}", UTF_16LE));
+    }
+
+    #[test]
+    fn legacy_single_byte_text_is_transcoded() {
+        let bytes = b"Caf\xE9 cr\xE8me br\xFBl\xE9e, na\xEFve fa\xE7ade\n";
+        let (text, encoding) = read_text(&bytes[..], None).unwrap();
+        assert_eq!(encoding, WINDOWS_1252);
+        assert_eq!(
+            text,
+            "Caf\u{e9} cr\u{e8}me br\u{fb}l\u{e9}e, na\u{ef}ve fa\u{e7}ade\n"
+        );
+    }
+
+    #[test]
+    fn a_character_cut_off_by_the_sample_is_still_utf8() {
+        let bytes = "caf\u{e9}".as_bytes();
+        let cut = &bytes[..bytes.len() - 1];
+        assert_eq!(detect(cut, false).unwrap(), UTF_8);
+        assert_ne!(detect(cut, true).unwrap(), UTF_8);
+    }
+
+    #[test]
+    fn utf32_is_rejected() {
+        let bytes = b"\xFF\xFE\x00\x00h\x00\x00\x00";
+        let err = detect(bytes, true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn lines(max_lines: usize) -> ReadLimit {
+        ReadLimit {
+            max_lines,
+            max_bytes: u64::MAX,
+        }
+    }
+
+    #[test]
+    fn max_lines_stops_reading_past_the_sample() {
+        // Lines of 3-byte characters, so some straddle the sample boundary.
+        let line = "\u{20ac}".repeat(100) + "\n";
+        let sample_lines = SAMPLE_SIZE / line.len();
+        let content = line.repeat(sample_lines * 2);
+        let (text, encoding) =
+            read_text(content.as_bytes(), Some(lines(sample_lines + 10))).unwrap();
+        assert_eq!(encoding, UTF_8);
+        assert!(text == line.repeat(sample_lines + 10));
+
+        let (text, _) = read_text(content.as_bytes(), Some(lines(usize::MAX))).unwrap();
+        assert!(text == content);
+    }
+
+    #[test]
+    fn max_bytes_cuts_back_to_the_last_complete_line() {
+        let content = "first\nsecond \u{20ac}\u{20ac}\nthird\n";
+        // Ends inside the second euro sign.
+        let max_bytes = "first\nsecond \u{20ac}".len() as u64 + 1;
+        let limit = ReadLimit {
+            max_lines: 10,
+            max_bytes,
+        };
+        let (text, encoding) = read_text(content.as_bytes(), Some(limit)).unwrap();
+        assert_eq!((text.as_str(), encoding), ("first\n", UTF_8));
+
+        let limit = ReadLimit {
+            max_lines: 1,
+            max_bytes: content.len() as u64,
+        };
+        let (text, _) = read_text(content.as_bytes(), Some(limit)).unwrap();
+        assert_eq!(text, "first\n");
+    }
+
+    #[test]
+    fn max_bytes_keeps_a_single_long_line() {
+        let limit = ReadLimit {
+            max_lines: 10,
+            max_bytes: 4,
+        };
+        let (text, _) = read_text(&b"abcdefgh"[..], Some(limit)).unwrap();
+        assert_eq!(text, "abcd");
+    }
+}
@@ -0,0 +1,791 @@
+use anyhow::Result;
+use content_inspector::{inspect, ContentType};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::encoding::{self, ReadLimit};
+use crate::output::{ContentOutput, PendingFile};
+use crate::progress::{ParsePhase, ProgressTracker};
+use crate::report::{SkipReason, SkippedPath};
+use crate::utils::{FileMetadata, LimitReason, LimitedFile, ParseOptions, ParsedPath};
+use crate::walk::{self, IgnoreRules, ParseRoot, SortOrder};
+
+/// Files read ahead of the writer at a time. Large enough to keep the pool
+/// busy, small enough that only a bounded amount of content sits in memory.
+const BATCH_SIZE: usize = 64;
+
+/// Leading bytes inspected to tell text from binary data.
+const SNIFF_SIZE: u64 = 8192;
+
+/// Everything a parse produced apart from the output files themselves.
+pub struct ParseResult {
+    pub tree: Vec<ParsedPath>,
+    pub files: Vec<FileMetadata>,
+    pub total_size: u64,
+    pub limited_files: Vec<LimitedFile>,
+    pub skipped: Vec<SkippedPath>,
+}
+
+/// Parses `paths` into `output`. The inputs are walked once in parallel,
+/// files are read and transformed on the rayon pool, and a writer thread
+/// writes them in walk order, so the output does not depend on which worker
+/// finishes first.
+pub fn run(
+    paths: &[String],
+    roots: &[ParseRoot],
+    options: &ParseOptions,
+    output: &mut ContentOutput,
+    progress: &ProgressTracker,
+) -> Result<ParseResult> {
+    let mut state = ParseState::new(options);
+    progress.set_phase(ParsePhase::Counting);
+    let inputs = walk_inputs(paths, roots, options.sort_order, progress, &mut state)?;
+
+    let mut files = Vec::new();
+    for input in &inputs {
+        input
+            .entry
+            .collect_files(input.root, &mut files, &mut state.skipped);
+    }
+    progress.set_phase(ParsePhase::Writing);
+
+    let cancelled = || roots.iter().any(|root| root.cancel.is_cancelled());
+    let (sender, receiver) = mpsc::sync_channel::<Vec<PreparedFile>>(2);
+    let limits = Limits {
+        max_file_size: options.max_file_size,
+        truncate_lines: options.truncate_lines,
+    };
+
+    thread::scope(|scope| {
+        let state = &mut state;
+        let writer = scope.spawn(move || -> Result<()> {
+            for batch in receiver {
+                // Dropping the receiver also stops the readers.
+                if cancelled() {
+                    break;
+                }
+                let handled: Vec<(String, u64)> = batch
+                    .iter()
+                    .map(|file| (file.display_path.clone(), file.size.unwrap_or(0)))
+                    .collect();
+                let pending: Vec<PendingFile> = batch
+                    .into_iter()
+                    .filter_map(|file| state.admit(file))
+                    .collect();
+                output.write_files(&pending)?;
+                for (path, size) in &handled {
+                    progress.file_done(path, *size);
+                }
+            }
+            Ok(())
+        });
+
+        for batch in files.chunks(BATCH_SIZE) {
+            if cancelled() {
+                break;
+            }
+            let prepared = batch
+                .par_iter()
+                .map(|(path, root)| prepare_file(path, root, &limits))
+                .collect();
+            if sender.send(prepared).is_err() {
+                break;
+            }
+        }
+        drop(sender);
+
+        writer
+            .join()
+            .map_err(|_| anyhow::anyhow!("Output writer panicked"))?
+    })?;
+    for root in roots {
+        root.cancel.check()?;
+    }
+
+    progress.set_phase(ParsePhase::Tree);
+    let mut tree: Vec<ParsedPath> = inputs
+        .iter()
+        .filter_map(|input| input.entry.to_tree(input.root, &state.tree_sizes))
+        .collect();
+    for node in &mut tree {
+        node.apply_encodings(&state.encodings);
+    }
+
+    Ok(ParseResult {
+        tree,
+        files: state.files,
+        total_size: state.total_size,
+        limited_files: state.limited_files,
+        skipped: state.skipped,
+    })
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Walking
+// /////////////////////////////////////////////////////////////////////////////
+
+enum WalkEntry {
+    File(PathBuf),
+    Dir {
+        path: PathBuf,
+        children: Vec<WalkEntry>,
+        /// Entries of this directory that were left out, and why.
+        skipped: Vec<(PathBuf, SkipReason)>,
+        /// Set when the directory itself could not be listed.
+        error: Option<io::Error>,
+    },
+}
+
+struct WalkedInput<'a> {
+    root: &'a ParseRoot,
+    entry: WalkEntry,
+}
+
+fn walk_inputs<'a>(
+    paths: &[String],
+    roots: &'a [ParseRoot],
+    order: SortOrder,
+    progress: &ProgressTracker,
+    state: &mut ParseState,
+) -> Result<Vec<WalkedInput<'a>>> {
+    let mut inputs = Vec::new();
+    for (path_str, root) in paths.iter().zip(roots) {
+        root.cancel.check()?;
+        let path = Path::new(path_str);
+        if path.is_symlink() {
+            state.skip(root.display_path(path), SkipReason::Symlink, None);
+            continue;
+        }
+        if !path.exists() {
+            let detail = Some("Path does not exist".to_string());
+            state.skip(root.display_path(path), SkipReason::Error, detail);
+            continue;
+        }
+        if !root.filter.allows(path, path.is_dir()) {
+            state.skip(root.display_path(path), SkipReason::Excluded, None);
+            continue;
+        }
+
+        let entry = if path.is_dir() {
+            walk_dir(path, &IgnoreRules::for_dir(path), root, order, progress)?
+        } else {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            progress.found(1, size);
+            WalkEntry::File(path.to_path_buf())
+        };
+        inputs.push(WalkedInput { root, entry });
+    }
+    Ok(inputs)
+}
+
+/// Lists `dir` and walks its subdirectories in parallel. Children are put in
+/// `order` first, since `read_dir` returns them in no particular order.
+fn walk_dir(
+    dir: &Path,
+    rules: &IgnoreRules,
+    root: &ParseRoot,
+    order: SortOrder,
+    progress: &ProgressTracker,
+) -> Result<WalkEntry> {
+    root.cancel.check()?;
+    let listing = match walk::read_dir_classified(dir, rules, &root.filter) {
+        Ok(listing) => listing,
+        Err(e) => {
+            return Ok(WalkEntry::Dir {
+                path: dir.to_path_buf(),
+                children: Vec::new(),
+                skipped: Vec::new(),
+                error: Some(e),
+            })
+        }
+    };
+
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut children: Vec<(PathBuf, bool)> = Vec::with_capacity(listing.children.len());
+    for path in listing.children {
+        let metadata = fs::metadata(&path).ok();
+        let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+        if !is_dir {
+            files += 1;
+            bytes += metadata.map_or(0, |m| m.len());
+        }
+        children.push((path, is_dir));
+    }
+    progress.found(files, bytes);
+    order.sort(&mut children);
+    let mut skipped = listing.skipped;
+    skipped.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let children = children
+        .into_par_iter()
+        .map(|(path, is_dir)| {
+            if is_dir {
+                walk_dir(&path, &rules.descend(&path), root, order, progress)
+            } else {
+                Ok(WalkEntry::File(path))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(WalkEntry::Dir {
+        path: dir.to_path_buf(),
+        children,
+        skipped,
+        error: None,
+    })
+}
+
+impl WalkEntry {
+    /// Appends the files below this entry in walk order, and records the
+    /// entries the walk left out along the way.
+    fn collect_files<'a>(
+        &'a self,
+        root: &'a ParseRoot,
+        files: &mut Vec<(&'a Path, &'a ParseRoot)>,
+        skipped: &mut Vec<SkippedPath>,
+    ) {
+        match self {
+            WalkEntry::File(path) => files.push((path, root)),
+            WalkEntry::Dir {
+                path,
+                children,
+                skipped: dir_skipped,
+                error,
+            } => {
+                if let Some(e) = error {
+                    skipped.push(SkippedPath {
+                        path: root.display_path(path),
+                        reason: SkipReason::from_io_error(e),
+                        detail: Some(e.to_string()),
+                    });
+                }
+                for (path, reason) in dir_skipped {
+                    skipped.push(SkippedPath {
+                        path: root.display_path(path),
+                        reason: *reason,
+                        detail: None,
+                    });
+                }
+                for child in children {
+                    child.collect_files(root, files, skipped);
+                }
+            }
+        }
+    }
+
+    /// Files that could not be opened are left out of the tree, and binary
+    /// files show up with a size of zero.
+    fn to_tree(&self, root: &ParseRoot, sizes: &HashMap<PathBuf, u64>) -> Option<ParsedPath> {
+        let path = match self {
+            WalkEntry::File(path) | WalkEntry::Dir { path, .. } => path,
+        };
+        let name = path.file_name()?.to_string_lossy().to_string();
+
+        match self {
+            WalkEntry::File(path) => Some(ParsedPath::File {
+                name,
+                path: root.display_path(path),
+                size: *sizes.get(path)?,
+                tokens: 0,
+                encoding: None,
+                content_range: None,
+            }),
+            WalkEntry::Dir { children, .. } => {
+                let children: Vec<ParsedPath> = children
+                    .iter()
+                    .filter_map(|child| child.to_tree(root, sizes))
+                    .collect();
+                let size = children
+                    .iter()
+                    .map(|child| match child {
+                        ParsedPath::File { size, .. } | ParsedPath::Directory { size, .. } => *size,
+                    })
+                    .sum();
+                Some(ParsedPath::Directory {
+                    name,
+                    path: root.display_path(path),
+                    size,
+                    tokens: 0,
+                    children,
+                })
+            }
+        }
+    }
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Reading
+// /////////////////////////////////////////////////////////////////////////////
+
+struct Limits {
+    max_file_size: Option<u64>,
+    truncate_lines: Option<usize>,
+}
+
+/// A file as read on the worker pool, waiting for its turn to be written.
+struct PreparedFile {
+    display_path: String,
+    source: PathBuf,
+    /// Size on disk, `None` when the file could not be opened.
+    size: Option<u64>,
+    is_text: bool,
+    content: Prepared,
+}
+
+enum Prepared {
+    Text {
+        content: String,
+        /// Length of the text before the transform, which is what counts
+        /// towards the total size limit.
+        read_size: u64,
+        encoding: &'static encoding_rs::Encoding,
+        truncation: Option<Truncation>,
+    },
+    OverFileSize,
+    Skipped(SkipReason, Option<String>),
+}
+
+/// How much of a file over the size limit was kept.
+struct Truncation {
+    lines: usize,
+    /// Set when the size limit cut the file short before `truncate_lines`
+    /// lines were read.
+    bytes: Option<u64>,
+}
+
+/// Opens the file once: the first bytes decide whether it is text, and the
+/// same handle is then read to the end or up to the truncation limits.
+fn prepare_file(path: &Path, root: &ParseRoot, limits: &Limits) -> PreparedFile {
+    let mut prepared = PreparedFile {
+        display_path: root.display_path(path),
+        source: path.to_path_buf(),
+        size: None,
+        is_text: false,
+        content: Prepared::Skipped(SkipReason::Error, None),
+    };
+    // The rest of a cancelled batch is left unread, the parse is discarded.
+    if root.cancel.is_cancelled() {
+        return prepared;
+    }
+    if let Err(e) = read_prepared(path, root, limits, &mut prepared) {
+        let reason = SkipReason::from_io_error(&e);
+        prepared.content = Prepared::Skipped(reason, Some(e.to_string()));
+    }
+    prepared
+}
+
+fn read_prepared(
+    path: &Path,
+    root: &ParseRoot,
+    limits: &Limits,
+    prepared: &mut PreparedFile,
+) -> io::Result<()> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    prepared.size = Some(metadata.len());
+    if !metadata.is_file() {
+        let detail = Some("Not a regular file".to_string());
+        prepared.content = Prepared::Skipped(SkipReason::Error, detail);
+        return Ok(());
+    }
+
+    let mut head = Vec::new();
+    (&mut file).take(SNIFF_SIZE).read_to_end(&mut head)?;
+    if !head.is_empty() && matches!(inspect(&head), ContentType::BINARY) {
+        prepared.content = Prepared::Skipped(SkipReason::Binary, None);
+        return Ok(());
+    }
+    prepared.is_text = true;
+
+    let limit = match (limits.max_file_size, limits.truncate_lines) {
+        (Some(max_bytes), Some(max_lines)) if metadata.len() > max_bytes => Some(ReadLimit {
+            max_lines,
+            max_bytes,
+        }),
+        (Some(max_bytes), None) if metadata.len() > max_bytes => {
+            prepared.content = Prepared::OverFileSize;
+            return Ok(());
+        }
+        _ => None,
+    };
+
+    let (content, encoding) = encoding::read_text(head.as_slice().chain(file), limit)?;
+    let read_size = content.len() as u64;
+    let truncation = limit.map(|limit| {
+        let lines = content.matches('\n').count();
+        Truncation {
+            lines: lines.min(limit.max_lines),
+            bytes: (lines < limit.max_lines).then_some(read_size),
+        }
+    });
+    let content = root.transform.apply(path, &content).into_owned();
+    prepared.content = Prepared::Text {
+        content,
+        read_size,
+        encoding,
+        truncation,
+    };
+    Ok(())
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Writing
+// /////////////////////////////////////////////////////////////////////////////
+
+/// Files admitted so far and the size limits they are checked against. Only
+/// the writer thread touches it, in walk order.
+struct ParseState {
+    max_file_size: Option<u64>,
+    max_total_size: Option<u64>,
+    files: Vec<FileMetadata>,
+    /// Encoding of each written file, keyed by output path.
+    encodings: HashMap<String, String>,
+    /// Size each file is shown with in the tree.
+    tree_sizes: HashMap<PathBuf, u64>,
+    total_size: u64,
+    limited_files: Vec<LimitedFile>,
+    skipped: Vec<SkippedPath>,
+}
+
+impl ParseState {
+    fn new(options: &ParseOptions) -> Self {
+        ParseState {
+            max_file_size: options.max_file_size,
+            max_total_size: options.max_total_size,
+            files: Vec::new(),
+            encodings: HashMap::new(),
+            tree_sizes: HashMap::new(),
+            total_size: 0,
+            limited_files: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Checks a file against the limits in walk order and records it.
+    /// Returns the file when it should be written.
+    fn admit(&mut self, file: PreparedFile) -> Option<PendingFile> {
+        let PreparedFile {
+            display_path,
+            source,
+            size,
+            is_text,
+            content,
+        } = file;
+        let Some(size) = size else {
+            if let Prepared::Skipped(reason, detail) = content {
+                self.skip(display_path, reason, detail);
+            }
+            return None;
+        };
+        self.tree_sizes
+            .insert(source.clone(), if is_text { size } else { 0 });
+
+        let (content, read_size, encoding, truncation) = match content {
+            Prepared::Text {
+                content,
+                read_size,
+                encoding,
+                truncation,
+            } => (content, read_size, encoding, truncation),
+            Prepared::OverFileSize => {
+                self.limit(display_path, size, LimitReason::MaxFileSize, None);
+                return None;
+            }
+            Prepared::Skipped(reason, detail) => {
+                self.skip(display_path, reason, detail);
+                return None;
+            }
+        };
+
+        if self.exceeds_total(read_size) {
+            self.limit(display_path, size, LimitReason::MaxTotalSize, None);
+            return None;
+        }
+        if truncation.is_some() {
+            let reason = LimitReason::MaxFileSize;
+            self.limit(display_path.clone(), size, reason, truncation);
+        }
+
+        self.total_size += read_size;
+        self.encodings
+            .insert(display_path.clone(), encoding.name().to_string());
+        self.files.push(FileMetadata {
+            name: source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: source.to_string_lossy().to_string(),
+            size,
+            encoding: Some(encoding.name().to_string()),
+        });
+        Some(PendingFile {
+            path: display_path,
+            source,
+            content,
+        })
+    }
+
+    fn exceeds_total(&self, size: u64) -> bool {
+        self.max_total_size
+            .is_some_and(|max| self.total_size + size > max)
+    }
+
+    /// Files that were left out entirely also go into the report, while
+    /// truncated ones only show up in `limited_files`.
+    fn limit(
+        &mut self,
+        path: String,
+        size: u64,
+        reason: LimitReason,
+        truncation: Option<Truncation>,
+    ) {
+        if truncation.is_none() {
+            let detail = match reason {
+                LimitReason::MaxFileSize => format!(
+                    "{} bytes, over the {} byte file size limit",
+                    size,
+                    self.max_file_size.unwrap_or_default()
+                ),
+                LimitReason::MaxTotalSize => format!(
+                    "{} bytes, would exceed the {} byte total size limit",
+                    size,
+                    self.max_total_size.unwrap_or_default()
+                ),
+            };
+            self.skip(path.clone(), SkipReason::Oversized, Some(detail));
+        }
+        self.limited_files.push(LimitedFile {
+            path,
+            size,
+            reason,
+            truncated_to_lines: truncation.as_ref().map(|t| t.lines),
+            truncated_to_bytes: truncation.and_then(|t| t.bytes),
+        });
+    }
+
+    fn skip(&mut self, path: String, reason: SkipReason, detail: Option<String>) {
+        self.skipped.push(SkippedPath {
+            path,
+            reason,
+            detail,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ChannelSink, ParseEvent};
+    use crate::summary::SourceInfo;
+    use crate::walk::PathFilter;
+    use std::fs;
+    use tempfile::TempDir;
+
+    struct Parsed {
+        content: String,
+        tree: String,
+        files: Vec<String>,
+        total_size: u64,
+        limited_files: Vec<LimitedFile>,
+        skipped: Vec<SkippedPath>,
+        walked_files: usize,
+    }
+
+    fn parse(input: &Path, options: &ParseOptions) -> Parsed {
+        let out = TempDir::new().unwrap();
+        let content_path = out.path().join("content.txt");
+        let paths = vec![input.to_string_lossy().to_string()];
+        let roots = vec![ParseRoot::new(input, &PathFilter::default(), false)];
+
+        let mut output = ContentOutput::new(&content_path, options).unwrap();
+        let (sink, events) = ChannelSink::new();
+        let progress = ProgressTracker::new("parse", &sink);
+        let result = run(&paths, &roots, options, &mut output, &progress).unwrap();
+        output.finish(&SourceInfo::default()).unwrap();
+        let walked_files = events
+            .try_iter()
+            .filter_map(|event| match event {
+                ParseEvent::Progress(progress) => Some(progress.files_amount),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+
+        Parsed {
+            content: fs::read_to_string(content_path).unwrap(),
+            tree: serde_json::to_string(&result.tree).unwrap(),
+            files: result.files.into_iter().map(|f| f.path).collect(),
+            total_size: result.total_size,
+            limited_files: result.limited_files,
+            skipped: result.skipped,
+            walked_files,
+        }
+    }
+
+    #[test]
+    fn parsing_the_same_tree_twice_gives_the_same_output() {
+        let input = TempDir::new().unwrap();
+        // More files than fit in one batch, spread over nested directories.
+        for dir in 0..6 {
+            let dir_path = input.path().join(format!("dir{}", dir)).join("nested");
+            fs::create_dir_all(&dir_path).unwrap();
+            for file in 0..60 {
+                let content = format!("file {} in {}\n", file, dir).repeat(file + 1);
+                fs::write(dir_path.join(format!("f{:02}.txt", file)), content).unwrap();
+            }
+        }
+        fs::write(input.path().join("image.bin"), [0u8, 159, 146, 150]).unwrap();
+        let options = ParseOptions::default();
+
+        let first = parse(input.path(), &options);
+        let second = parse(input.path(), &options);
+
+        assert_eq!(first.walked_files, 361);
+        assert_eq!(first.files.len(), 360);
+        assert_eq!(first.skipped.len(), 1);
+        assert!(first.content == second.content);
+        assert!(first.tree == second.tree);
+        assert!(first.files == second.files);
+        assert_eq!(first.total_size, second.total_size);
+        assert_eq!(first.skipped.len(), second.skipped.len());
+
+        let mut sorted = first.files.clone();
+        sorted.sort();
+        assert!(first.files == sorted);
+    }
+
+    fn file_names(parsed: &Parsed) -> Vec<&str> {
+        parsed
+            .files
+            .iter()
+            .map(|path| path.rsplit(['/', '\\']).next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn files_over_the_size_limit_are_skipped() {
+        let input = TempDir::new().unwrap();
+        fs::write(input.path().join("big.txt"), "x".repeat(100)).unwrap();
+        fs::write(input.path().join("small.txt"), "small\n").unwrap();
+        let options = ParseOptions {
+            max_file_size: Some(50),
+            ..ParseOptions::default()
+        };
+
+        let parsed = parse(input.path(), &options);
+
+        assert_eq!(file_names(&parsed), ["small.txt"]);
+        assert_eq!(parsed.total_size, 6);
+        assert!(!parsed.content.contains("xxx"));
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].path, "big.txt");
+        assert_eq!(parsed.skipped[0].reason, SkipReason::Oversized);
+        assert_eq!(
+            parsed.skipped[0].detail.as_deref(),
+            Some("100 bytes, over the 50 byte file size limit")
+        );
+        assert_eq!(parsed.limited_files.len(), 1);
+        assert_eq!(parsed.limited_files[0].reason, LimitReason::MaxFileSize);
+        assert_eq!(parsed.limited_files[0].truncated_to_lines, None);
+    }
+
+    #[test]
+    fn files_past_the_total_size_limit_are_skipped() {
+        let input = TempDir::new().unwrap();
+        fs::write(input.path().join("a.txt"), "a".repeat(30)).unwrap();
+        fs::write(input.path().join("b.txt"), "b".repeat(30)).unwrap();
+        fs::write(input.path().join("c.txt"), "c".repeat(10)).unwrap();
+        let options = ParseOptions {
+            max_total_size: Some(45),
+            ..ParseOptions::default()
+        };
+
+        let parsed = parse(input.path(), &options);
+
+        // Smaller files later in walk order still fit.
+        assert_eq!(file_names(&parsed), ["a.txt", "c.txt"]);
+        assert_eq!(parsed.total_size, 40);
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].path, "b.txt");
+        assert_eq!(parsed.skipped[0].reason, SkipReason::Oversized);
+        assert_eq!(
+            parsed.skipped[0].detail.as_deref(),
+            Some("30 bytes, would exceed the 45 byte total size limit")
+        );
+        assert_eq!(parsed.limited_files[0].reason, LimitReason::MaxTotalSize);
+    }
+
+    #[test]
+    fn oversized_files_are_truncated_by_lines_and_bytes() {
+        let input = TempDir::new().unwrap();
+        let lines: String = (1..=20).map(|i| format!("l{}\n", i)).collect();
+        fs::write(input.path().join("lines.txt"), lines).unwrap();
+        let wide = format!(
+            "{}\n{}\n{}\n",
+            "a".repeat(10),
+            "b".repeat(10),
+            "c".repeat(10)
+        );
+        fs::write(input.path().join("wide.txt"), wide).unwrap();
+        let options = ParseOptions {
+            max_file_size: Some(20),
+            truncate_lines: Some(2),
+            ..ParseOptions::default()
+        };
+
+        let parsed = parse(input.path(), &options);
+
+        assert_eq!(file_names(&parsed), ["lines.txt", "wide.txt"]);
+        assert!(parsed.skipped.is_empty());
+        assert!(parsed.content.contains("l1\nl2\n") && !parsed.content.contains("l3"));
+        assert!(parsed.content.contains("aaaaaaaaaa\n") && !parsed.content.contains('b'));
+        assert_eq!(parsed.total_size, 6 + 11);
+
+        let limited: Vec<(&str, Option<usize>, Option<u64>)> = parsed
+            .limited_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.truncated_to_lines, f.truncated_to_bytes))
+            .collect();
+        assert_eq!(
+            limited,
+            [
+                ("lines.txt", Some(2), None),
+                ("wide.txt", Some(1), Some(11))
+            ]
+        );
+        assert!(parsed
+            .limited_files
+            .iter()
+            .all(|f| f.reason == LimitReason::MaxFileSize));
+    }
+
+    #[test]
+    fn undecodable_text_is_skipped_with_the_reason() {
+        let input = TempDir::new().unwrap();
+        let utf32: Vec<u8> = [0xFF, 0xFE, 0x00, 0x00]
+            .into_iter()
+            .chain("hi".chars().flat_map(|c| (c as u32).to_le_bytes()))
+            .collect();
+        fs::write(input.path().join("wide.txt"), utf32).unwrap();
+        fs::write(input.path().join("ok.txt"), "ok\n").unwrap();
+
+        let parsed = parse(input.path(), &ParseOptions::default());
+
+        assert_eq!(file_names(&parsed), ["ok.txt"]);
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].path, "wide.txt");
+        assert_eq!(parsed.skipped[0].reason, SkipReason::Undecodable);
+        assert_eq!(
+            parsed.skipped[0].detail.as_deref(),
+            Some("UTF-32 text is not supported")
+        );
+    }
+}
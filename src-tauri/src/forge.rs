@@ -0,0 +1,145 @@
+/// A repository URL as pasted by the user, split into the repository to
+/// clone and the part of it to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoUrl {
+    pub clone_url: String,
+    /// Whatever follows the marker of a tree, blob or commit URL: a ref, then
+    /// optionally a path inside the repository. Refs can contain slashes, so
+    /// where the ref ends is only known once the remote's refs are listed.
+    pub ref_path: Option<String>,
+}
+
+/// Splits web URLs of the common forges into repository and ref path:
+///
+/// - GitHub: `<owner>/<repo>/tree/<ref>/<path>`, also `blob` and `commit`
+/// - GitLab: `<group>/.../<repo>/-/tree/<ref>/<path>`, also `blob` and `commit`
+/// - Gitea, Forgejo: `<owner>/<repo>/src/branch/<ref>/<path>`, also `tag` and `commit`
+/// - Bitbucket: `<owner>/<repo>/src/<ref>/<path>`
+///
+/// The patterns are matched on any host, so self-hosted instances work too.
+/// Anything else is returned unchanged as the clone URL.
+pub fn parse_repo_url(input: &str) -> RepoUrl {
+    let unchanged = RepoUrl {
+        clone_url: input.to_string(),
+        ref_path: None,
+    };
+
+    let Some((scheme, rest)) = input.split_once("://") else {
+        return unchanged;
+    };
+    if scheme != "https" && scheme != "http" {
+        return unchanged;
+    }
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let Some((repo_len, marker_len)) = find_marker(&segments) else {
+        return unchanged;
+    };
+    let ref_path = &segments[repo_len + marker_len..];
+    if ref_path.is_empty() {
+        return unchanged;
+    }
+
+    RepoUrl {
+        clone_url: format!(
+            "{}://{}/{}",
+            scheme,
+            authority,
+            segments[..repo_len].join("/")
+        ),
+        ref_path: Some(ref_path.join("/")),
+    }
+}
+
+/// Number of segments naming the repository and the length of the marker
+/// that follows them.
+fn find_marker(segments: &[&str]) -> Option<(usize, usize)> {
+    // GitLab repositories can be nested in any number of groups, but the
+    // `-` separator makes the marker unambiguous.
+    let gitlab = segments
+        .windows(2)
+        .position(|pair| pair[0] == "-" && matches!(pair[1], "tree" | "blob" | "commit"));
+    if let Some(index) = gitlab.filter(|&index| index >= 2) {
+        return Some((index, 2));
+    }
+
+    match segments {
+        [_, _, "src", "branch" | "tag" | "commit", ..] => Some((2, 2)),
+        [_, _, "tree" | "blob" | "commit" | "src", ..] => Some((2, 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(input: &str) -> (String, Option<String>) {
+        let url = parse_repo_url(input);
+        (url.clone_url, url.ref_path)
+    }
+
+    fn split(clone_url: &str, ref_path: &str) -> (String, Option<String>) {
+        (clone_url.to_string(), Some(ref_path.to_string()))
+    }
+
+    #[test]
+    fn github_tree_blob_and_commit_urls() {
+        assert_eq!(
+            parsed("https://github.com/owner/repo/tree/feature/x/src/lib"),
+            split("https://github.com/owner/repo", "feature/x/src/lib")
+        );
+        assert_eq!(
+            parsed("https://github.com/owner/repo/blob/main/README.md?plain=1#L3"),
+            split("https://github.com/owner/repo", "main/README.md")
+        );
+        assert_eq!(
+            parsed("https://github.com/owner/repo/commit/abc123"),
+            split("https://github.com/owner/repo", "abc123")
+        );
+    }
+
+    #[test]
+    fn gitlab_urls_keep_nested_groups() {
+        assert_eq!(
+            parsed("https://gitlab.com/group/sub/repo/-/tree/main/docs"),
+            split("https://gitlab.com/group/sub/repo", "main/docs")
+        );
+    }
+
+    #[test]
+    fn gitea_and_bitbucket_src_urls() {
+        assert_eq!(
+            parsed("https://codeberg.org/owner/repo/src/branch/main/src"),
+            split("https://codeberg.org/owner/repo", "main/src")
+        );
+        assert_eq!(
+            parsed("https://bitbucket.org/owner/repo/src/v1.0/lib"),
+            split("https://bitbucket.org/owner/repo", "v1.0/lib")
+        );
+    }
+
+    #[test]
+    fn self_hosted_instances_are_recognized() {
+        assert_eq!(
+            parsed("http://git.example.com:8080/owner/repo/tree/dev"),
+            split("http://git.example.com:8080/owner/repo", "dev")
+        );
+    }
+
+    #[test]
+    fn other_urls_are_returned_unchanged() {
+        for input in [
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo.git",
+            "https://github.com/owner/repo/tree",
+            "https://github.com/owner/repo/issues/1",
+            "git@github.com:owner/repo/tree/main",
+            "ssh://git@github.com/owner/repo/tree/main",
+        ] {
+            assert_eq!(parsed(input), (input.to_string(), None), "{}", input);
+        }
+    }
+}
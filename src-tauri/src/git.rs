@@ -0,0 +1,898 @@
+use anyhow::Result;
+use git2::build::CheckoutBuilder;
+use git2::{
+    Cred, CredentialType, Direction, FetchOptions, Oid, Progress, Remote, RemoteCallbacks,
+    Repository,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use uuid::Uuid;
+
+use crate::cache;
+use crate::credentials::{self, Credential};
+use crate::forge::{self, RepoUrl};
+use crate::progress::{ProgressSink, EMIT_INTERVAL};
+use crate::summary::SourceInfo;
+use crate::utils::{get_app_dir, strip_url_credentials, TEMP_REPOS_DIR};
+
+/// libgit2's fetch depth for fetching the whole history of a shallow
+/// repository.
+const UNSHALLOW: i32 = i32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClonePhase {
+    /// Downloading objects from the remote.
+    Receiving,
+    /// Resolving the deltas of the received pack.
+    Resolving,
+    CheckingOut,
+}
+
+/// Payload of the `clone-progress` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneProgress {
+    pub url: String,
+    pub phase: ClonePhase,
+    /// Objects received, deltas resolved or files checked out, depending on
+    /// the phase.
+    pub done: usize,
+    pub total: usize,
+    pub received_bytes: usize,
+}
+
+/// Checks out `git_ref` of `url` into a fresh directory under `temp-repos`
+/// and returns the checkout. `git_ref` can be a branch, a tag or a full or
+/// short commit SHA, and defaults to the remote's default branch.
+///
+/// The objects come from a bare mirror of the remote in the repository
+/// cache, which only has to fetch what it is missing, so parsing the same
+/// repository again does not download it again.
+///
+/// `url` can also be a forge's tree or blob URL. Its ref is used unless
+/// `git_ref` is given, only its path is checked out, and the path inside the
+/// checkout is returned.
+pub fn clone_git_repo(
+    url: &str,
+    git_ref: Option<&str>,
+    sink: &dyn ProgressSink,
+) -> Result<PathBuf> {
+    let app_dir = get_app_dir()?;
+    let temp_dir = app_dir.join(TEMP_REPOS_DIR);
+    if !temp_dir.exists() {
+        fs::create_dir_all(&temp_dir)?;
+    }
+    let cache_dir = cache::get_cache_dir()?;
+    if !cache_dir.exists() {
+        fs::create_dir_all(&cache_dir)?;
+    }
+
+    // Cloning into `<uuid>/<repo name>` keeps the checkout's own folder name
+    // meaningful, since output paths can be prefixed with it.
+    let repo_url = forge::parse_repo_url(url);
+    let repo_name = repo_name_from_url(&repo_url.clone_url);
+    let clone_dir = temp_dir.join(Uuid::new_v4().to_string());
+    let target_path = clone_dir.join(&repo_name);
+    fs::create_dir_all(&clone_dir)?;
+
+    // Credentials only ever reach libgit2 through the credentials callback,
+    // never the URL, so they cannot leak into the remote URL or parse id.
+    let transport = Transport {
+        reporter: CloneReporter::new(&repo_url.clone_url, sink),
+        credential: credentials::credential_for_url(&repo_url.clone_url),
+    };
+
+    let mirror_name = cache::mirror_dir_name(&repo_url.clone_url, &repo_name);
+    let mirror_path = cache_dir.join(&mirror_name);
+    let result = {
+        let lock = cache::mirror_lock(&mirror_name);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let is_new = !mirror_path.exists();
+        let result = clone_ref(&repo_url, git_ref, &mirror_path, &target_path, &transport);
+        // The checkout reads its objects from the mirror through alternates,
+        // so the mirror has to outlive it.
+        if result.is_ok() {
+            cache::retain_checkout(&clone_dir, &mirror_name);
+        }
+        if result.is_err() && is_new && mirror_path.exists() {
+            if let Err(e) = fs::remove_dir_all(&mirror_path) {
+                eprintln!("Failed to remove failed mirror {:?}: {}", mirror_path, e);
+            }
+        }
+        result
+    };
+
+    match result {
+        Ok(subpath) => {
+            if let Err(e) = cache::record_use(&repo_url.clone_url, &mirror_name) {
+                eprintln!("Failed to update the repository cache: {}", e);
+            }
+            Ok(match subpath {
+                Some(subpath) => target_path.join(subpath),
+                None => target_path,
+            })
+        }
+        Err(e) => {
+            if let Err(e) = fs::remove_dir_all(&clone_dir) {
+                eprintln!("Failed to remove failed clone {:?}: {}", clone_dir, e);
+            }
+            Err(anyhow::anyhow!(
+                "Git clone of {} failed: {}",
+                strip_url_credentials(url),
+                e.message()
+            ))
+        }
+    }
+}
+
+/// What a requested ref turned out to be on the remote.
+enum ResolvedRef {
+    Branch(String),
+    Tag(String),
+    /// A full or abbreviated commit SHA, lowercased.
+    Commit(String),
+}
+
+/// Brings the mirror at `mirror_path` up to date with the requested ref,
+/// checks that out into `target` and returns the path inside the repository
+/// the URL pointed at, if any.
+fn clone_ref(
+    repo_url: &RepoUrl,
+    git_ref: Option<&str>,
+    mirror_path: &Path,
+    target: &Path,
+    transport: &Transport,
+) -> Result<Option<String>, git2::Error> {
+    let url = repo_url.clone_url.as_str();
+    let is_new = !mirror_path.exists();
+    let mirror = if is_new {
+        Repository::init_bare(mirror_path)?
+    } else {
+        Repository::open_bare(mirror_path)?
+    };
+    // An anonymous remote keeps the URL out of the mirror's config, so a
+    // mirror is only ever tied to its cache key.
+    let mut remote = mirror.remote_anonymous(url)?;
+
+    let (heads, default_branch) = {
+        let connection =
+            remote.connect_auth(Direction::Fetch, Some(transport.callbacks()), None)?;
+        let heads: Vec<(String, Oid)> = connection
+            .list()?
+            .iter()
+            .map(|head| (head.name().to_string(), head.oid()))
+            .collect();
+        let default_branch = connection
+            .default_branch()
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string));
+        (heads, default_branch)
+    };
+
+    let (url_ref, subpath) = match &repo_url.ref_path {
+        Some(ref_path) => split_ref_path(ref_path, &heads),
+        None => (None, None),
+    };
+    let requested = git_ref.or(url_ref.as_deref());
+    let resolved = resolve_ref(requested, &heads, default_branch.as_deref())?;
+    // libgit2's local transport cannot do shallow fetches, and copying
+    // local objects is cheap anyway. A mirror that already has the full
+    // history keeps it.
+    let shallow = !is_local_url(url) && (is_new || mirror.is_shallow());
+    let depth = shallow.then_some(1);
+    let commit_id = fetch_resolved(&mirror, &mut remote, &resolved, &heads, depth, transport)?;
+
+    let repo = init_worktree(&mirror, target, url)?;
+    let commit = repo.find_commit(commit_id)?;
+
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().progress(|_, done, total| {
+        transport
+            .reporter
+            .report(ClonePhase::CheckingOut, done, total)
+    });
+    // A sparse checkout: only the files below the subpath are written.
+    if let Some(subpath) = &subpath {
+        checkout.path(subpath);
+    }
+    repo.checkout_tree(commit.as_object(), Some(&mut checkout))?;
+
+    match &resolved {
+        ResolvedRef::Branch(name) => {
+            repo.branch(name, &commit, true)?;
+            repo.set_head(&format!("refs/heads/{}", name))?;
+        }
+        ResolvedRef::Tag(_) | ResolvedRef::Commit(_) => repo.set_head_detached(commit_id)?,
+    }
+    copy_tags_at(&mirror, &repo, commit_id)?;
+
+    if let Some(subpath) = &subpath {
+        if !target.join(subpath).exists() {
+            return Err(git2::Error::from_str(&format!(
+                "'{}' does not exist at {}",
+                subpath, commit_id
+            )));
+        }
+    }
+    Ok(subpath)
+}
+
+/// Creates an empty repository at `target` that borrows its objects from
+/// `mirror`, like `git clone --shared`, so a checkout copies no objects.
+fn init_worktree(mirror: &Repository, target: &Path, url: &str) -> Result<Repository, git2::Error> {
+    let repo = Repository::init(target)?;
+    repo.remote("origin", &strip_url_credentials(url))?;
+
+    let io_error = |e: std::io::Error| git2::Error::from_str(&e.to_string());
+    let objects_dir = mirror.path().join("objects");
+    fs::write(
+        repo.path().join("objects/info/alternates"),
+        format!("{}\n", objects_dir.display()),
+    )
+    .map_err(io_error)?;
+    // Without the mirror's shallow boundary, walking the history would run
+    // into parents that were never fetched.
+    let shallow = mirror.path().join("shallow");
+    if shallow.exists() {
+        fs::copy(shallow, repo.path().join("shallow")).map_err(io_error)?;
+    }
+
+    // Alternates are only read when a repository is opened.
+    Repository::open(target)
+}
+
+/// Gives the checkout the mirror's tags pointing at `commit`, so the ref a
+/// detached HEAD came from can still be told.
+fn copy_tags_at(mirror: &Repository, repo: &Repository, commit: Oid) -> Result<(), git2::Error> {
+    for name in mirror.tag_names(None)?.iter().flatten() {
+        let tag = mirror.find_reference(&format!("refs/tags/{}", name))?;
+        if tag
+            .peel_to_commit()
+            .is_ok_and(|tagged| tagged.id() == commit)
+        {
+            if let Some(target) = tag.target() {
+                repo.reference(&format!("refs/tags/{}", name), target, true, "")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splits the `<ref>/<path>` of a tree or blob URL at the longest prefix
+/// naming an advertised branch or tag. Otherwise the first segment is taken
+/// as the ref, which covers commit SHAs.
+fn split_ref_path(ref_path: &str, heads: &[(String, Oid)]) -> (Option<String>, Option<String>) {
+    let segments: Vec<&str> = ref_path.split('/').collect();
+    let is_ref = |name: &str| {
+        heads.iter().any(|(head, _)| {
+            head.strip_prefix("refs/heads/")
+                .or_else(|| head.strip_prefix("refs/tags/"))
+                == Some(name)
+        })
+    };
+    let ref_len = (1..=segments.len())
+        .rev()
+        .find(|&len| is_ref(&segments[..len].join("/")))
+        .unwrap_or(1);
+
+    let subpath = segments[ref_len..].join("/");
+    (
+        Some(segments[..ref_len].join("/")),
+        (!subpath.is_empty()).then_some(subpath),
+    )
+}
+
+/// Matches `requested` against the refs the remote advertised. Branches win
+/// over tags of the same name, as with `git clone --branch`.
+fn resolve_ref(
+    requested: Option<&str>,
+    heads: &[(String, Oid)],
+    default_branch: Option<&str>,
+) -> Result<ResolvedRef, git2::Error> {
+    let Some(requested) = requested.map(str::trim).filter(|r| !r.is_empty()) else {
+        return default_branch
+            .and_then(|name| name.strip_prefix("refs/heads/"))
+            .map(|name| ResolvedRef::Branch(name.to_string()))
+            .ok_or_else(|| git2::Error::from_str("the remote has no default branch"));
+    };
+    let advertised = |name: &str| heads.iter().any(|(head, _)| head == name);
+
+    let branch = requested.strip_prefix("refs/heads/").unwrap_or(requested);
+    if !requested.starts_with("refs/tags/") && advertised(&format!("refs/heads/{}", branch)) {
+        return Ok(ResolvedRef::Branch(branch.to_string()));
+    }
+    let tag = requested.strip_prefix("refs/tags/").unwrap_or(requested);
+    if !requested.starts_with("refs/heads/") && advertised(&format!("refs/tags/{}", tag)) {
+        return Ok(ResolvedRef::Tag(tag.to_string()));
+    }
+    if is_commit_sha(requested) {
+        return Ok(ResolvedRef::Commit(requested.to_ascii_lowercase()));
+    }
+
+    Err(git2::Error::from_str(&format!(
+        "no branch, tag or commit named '{}'",
+        requested
+    )))
+}
+
+/// Fetches just enough of the remote to check out `resolved` and returns the
+/// commit to check out.
+fn fetch_resolved(
+    repo: &Repository,
+    remote: &mut Remote,
+    resolved: &ResolvedRef,
+    heads: &[(String, Oid)],
+    depth: Option<i32>,
+    transport: &Transport,
+) -> Result<Oid, git2::Error> {
+    match resolved {
+        ResolvedRef::Branch(name) => {
+            let branch = format!("refs/heads/{}", name);
+            fetch(
+                remote,
+                &[mirror_refspec(&branch).as_str()],
+                depth,
+                transport,
+            )?;
+            repo.refname_to_id(&branch)
+        }
+        ResolvedRef::Tag(name) => {
+            let tag = format!("refs/tags/{}", name);
+            fetch(remote, &[mirror_refspec(&tag).as_str()], depth, transport)?;
+            Ok(repo.find_reference(&tag)?.peel_to_commit()?.id())
+        }
+        ResolvedRef::Commit(sha) => {
+            // A commit some advertised ref points at is fetched through that
+            // ref. Any other commit can only be asked for by its full SHA,
+            // which servers may refuse, in which case everything is fetched
+            // and the SHA looked up locally.
+            let advertised = heads
+                .iter()
+                .filter(|(name, _)| name.starts_with("refs/"))
+                .find(|(_, oid)| oid.to_string().starts_with(sha.as_str()));
+            let wanted = match advertised {
+                Some((name, oid)) => Some((mirror_refspec(name.trim_end_matches("^{}")), *oid)),
+                None if sha.len() == 40 => Oid::from_str(sha).ok().map(|oid| (sha.clone(), oid)),
+                None => None,
+            };
+            if let Some((refspec, oid)) = wanted {
+                let fetched = fetch(remote, &[refspec.as_str()], depth, transport)
+                    .and_then(|_| repo.find_object(oid, None))
+                    .and_then(|object| object.peel_to_commit());
+                if let Ok(commit) = fetched {
+                    return Ok(commit.id());
+                }
+            }
+
+            // A shallow mirror has to be deepened, or the commit may lie
+            // beyond its boundary.
+            let refspecs = ["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+            let depth = repo.is_shallow().then_some(UNSHALLOW);
+            fetch(remote, &refspecs, depth, transport)?;
+            Ok(repo.revparse_single(sha)?.peel_to_commit()?.id())
+        }
+    }
+}
+
+/// Refspec fetching the advertised ref `name` into the mirror under the
+/// same name, as `git clone --mirror` does.
+fn mirror_refspec(name: &str) -> String {
+    format!("+{0}:{0}", name)
+}
+
+fn fetch(
+    remote: &mut Remote,
+    refspecs: &[&str],
+    depth: Option<i32>,
+    transport: &Transport,
+) -> Result<(), git2::Error> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(transport.callbacks());
+    if let Some(depth) = depth {
+        options.depth(depth);
+    }
+    remote.fetch(refspecs, Some(&mut options), None)
+}
+
+fn is_commit_sha(name: &str) -> bool {
+    (4..=40).contains(&name.len()) && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+fn repo_name_from_url(url: &str) -> String {
+    let last_segment = url
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default();
+
+    let name: String = last_segment
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+
+    if name.is_empty() || name.starts_with('.') {
+        "repo".to_string()
+    } else {
+        name
+    }
+}
+
+/// Remote URL, checked out commit and ref of the git repository `root` is in.
+pub fn read_source_info(root: &Path, remote_url: &str) -> SourceInfo {
+    let repo = Repository::discover(root).ok();
+
+    let remote_url = if remote_url.is_empty() {
+        repo.as_ref().and_then(|repo| {
+            let origin = repo.find_remote("origin").ok()?;
+            origin.url().map(str::to_string)
+        })
+    } else {
+        Some(remote_url.to_string())
+    };
+
+    SourceInfo {
+        remote_url: remote_url.map(|url| strip_url_credentials(&url)),
+        commit: repo.as_ref().and_then(|repo| {
+            let commit = repo.head().ok()?.peel_to_commit().ok()?;
+            Some(commit.id().to_string())
+        }),
+        git_ref: repo.as_ref().and_then(head_ref_name),
+    }
+}
+
+/// The branch HEAD is on, or for a detached HEAD the first tag pointing at it.
+fn head_ref_name(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        return head.shorthand().map(str::to_string);
+    }
+
+    let commit = head.peel_to_commit().ok()?.id();
+    let tag_names = repo.tag_names(None).ok()?;
+    tag_names
+        .iter()
+        .flatten()
+        .filter(|name| {
+            repo.find_reference(&format!("refs/tags/{}", name))
+                .and_then(|tag| tag.peel_to_commit())
+                .is_ok_and(|tagged| tagged.id() == commit)
+        })
+        .min()
+        .map(str::to_string)
+}
+
+/// What every connection to the remote needs: progress reporting and the
+/// stored credential for its host.
+struct Transport<'a> {
+    reporter: CloneReporter<'a>,
+    credential: Option<Credential>,
+}
+
+impl Transport<'_> {
+    fn callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.transfer_progress(|stats| {
+            self.reporter.transfer(&stats);
+            true
+        });
+
+        let mut attempted = false;
+        callbacks.credentials(move |url, username_from_url, allowed| {
+            let username = username_from_url.unwrap_or("git");
+            if allowed.contains(CredentialType::USERNAME) {
+                return Cred::username(username);
+            }
+            let host = credentials::host_from_url(url).unwrap_or_default();
+            // libgit2 keeps asking for as long as authentication fails.
+            if attempted {
+                return Err(git2::Error::from_str(&format!(
+                    "authentication failed for {}",
+                    host
+                )));
+            }
+            attempted = true;
+
+            match &self.credential {
+                Some(Credential::Token { username, token })
+                    if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) =>
+                {
+                    Cred::userpass_plaintext(username.as_deref().unwrap_or("git"), token)
+                }
+                Some(Credential::SshKey {
+                    private_key_path,
+                    public_key_path,
+                    passphrase,
+                }) if allowed.contains(CredentialType::SSH_KEY) => Cred::ssh_key(
+                    username,
+                    public_key_path.as_deref().map(Path::new),
+                    Path::new(private_key_path),
+                    passphrase.as_deref(),
+                ),
+                None if allowed.contains(CredentialType::SSH_KEY) => {
+                    Cred::ssh_key_from_agent(username)
+                }
+                _ => Err(git2::Error::from_str(&format!(
+                    "{} requires authentication, but no matching credential is stored for it",
+                    host
+                ))),
+            }
+        });
+        callbacks
+    }
+}
+
+/// Turns libgit2's transfer and checkout callbacks into throttled
+/// `CloneProgress` reports.
+struct CloneReporter<'a> {
+    url: String,
+    sink: &'a dyn ProgressSink,
+    received_bytes: Cell<usize>,
+    last_report: RefCell<Option<(ClonePhase, Instant)>>,
+}
+
+impl<'a> CloneReporter<'a> {
+    fn new(url: &str, sink: &'a dyn ProgressSink) -> Self {
+        CloneReporter {
+            url: strip_url_credentials(url),
+            sink,
+            received_bytes: Cell::new(0),
+            last_report: RefCell::new(None),
+        }
+    }
+
+    fn transfer(&self, stats: &Progress) {
+        self.received_bytes.set(stats.received_bytes());
+        if stats.received_objects() < stats.total_objects() {
+            self.report(
+                ClonePhase::Receiving,
+                stats.received_objects(),
+                stats.total_objects(),
+            );
+        } else {
+            self.report(
+                ClonePhase::Resolving,
+                stats.indexed_deltas(),
+                stats.total_deltas(),
+            );
+        }
+    }
+
+    fn report(&self, phase: ClonePhase, done: usize, total: usize) {
+        let mut last_report = self.last_report.borrow_mut();
+        // Phase changes and the end of a phase are always reported.
+        let due = match *last_report {
+            Some((last_phase, at)) => {
+                last_phase != phase || done == total || at.elapsed() >= EMIT_INTERVAL
+            }
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        *last_report = Some((phase, Instant::now()));
+
+        self.sink.clone_progress(&CloneProgress {
+            url: self.url.clone(),
+            phase,
+            done,
+            total,
+            received_bytes: self.received_bytes.get(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ChannelSink, NoopSink, ParseEvent};
+    use crate::utils::TestAppDir;
+    use git2::Signature;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    /// A bare remote with the history `root <- one <- two`: `main` (the
+    /// default branch) at `two`, `feature/x` and the tag `v1` at `one`, and
+    /// `root` only reachable through them.
+    struct Remote {
+        dir: TempDir,
+        url: String,
+        root: Oid,
+        one: Oid,
+        two: Oid,
+    }
+
+    fn remote() -> Remote {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init_bare(dir.path().join("remote.git")).unwrap();
+        let root = commit(&repo, "refs/heads/main", &[("a.txt", "root")], &[]);
+        let one = commit(
+            &repo,
+            "refs/heads/main",
+            &[("a.txt", "one"), ("docs/guide.md", "guide")],
+            &[root],
+        );
+        let two = commit(
+            &repo,
+            "refs/heads/main",
+            &[("a.txt", "two"), ("docs/guide.md", "guide")],
+            &[one],
+        );
+        repo.reference("refs/heads/feature/x", one, true, "")
+            .unwrap();
+        repo.reference("refs/tags/v1", one, true, "").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+
+        let url = format!("file://{}", repo.path().display());
+        Remote {
+            dir,
+            url,
+            root,
+            one,
+            two,
+        }
+    }
+
+    fn commit(repo: &Repository, branch: &str, files: &[(&str, &str)], parents: &[Oid]) -> Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo.find_tree(write_tree(repo, files)).unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|&oid| repo.find_commit(oid).unwrap())
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(
+            Some(branch),
+            &signature,
+            &signature,
+            "commit",
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn write_tree(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let mut builder = repo.treebuilder(None).unwrap();
+        let mut dirs: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+        for &(path, content) in files {
+            match path.split_once('/') {
+                Some((dir, rest)) => dirs.entry(dir).or_default().push((rest, content)),
+                None => {
+                    let blob = repo.blob(content.as_bytes()).unwrap();
+                    builder.insert(path, blob, 0o100644).unwrap();
+                }
+            }
+        }
+        for (dir, files) in dirs {
+            builder
+                .insert(dir, write_tree(repo, &files), 0o040000)
+                .unwrap();
+        }
+        builder.write().unwrap()
+    }
+
+    fn checkout(
+        remote: &Remote,
+        ref_path: Option<&str>,
+        git_ref: Option<&str>,
+        name: &str,
+    ) -> Result<(PathBuf, Option<String>), git2::Error> {
+        let repo_url = RepoUrl {
+            clone_url: remote.url.clone(),
+            ref_path: ref_path.map(str::to_string),
+        };
+        let transport = Transport {
+            reporter: CloneReporter::new(&remote.url, &NoopSink),
+            credential: None,
+        };
+        let target = remote.dir.path().join(name);
+        let mirror = remote.dir.path().join("mirror.git");
+        let subpath = clone_ref(&repo_url, git_ref, &mirror, &target, &transport)?;
+        Ok((target, subpath))
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn clones_are_checked_out_under_temp_repos_with_progress() {
+        let app_dir = TestAppDir::new();
+        let remote = remote();
+        let (sink, events) = ChannelSink::new();
+
+        let target = clone_git_repo(&remote.url, None, &sink).unwrap();
+
+        assert!(target.starts_with(app_dir.dir.path().join(TEMP_REPOS_DIR)));
+        assert!(target.ends_with("remote"));
+        assert_eq!(read(&target.join("a.txt")), "two");
+        assert_eq!(read(&target.join("docs/guide.md")), "guide");
+
+        let clone_events: Vec<CloneProgress> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                ParseEvent::Clone(progress) => Some(progress),
+                _ => None,
+            })
+            .collect();
+        let last = clone_events.last().unwrap();
+        assert_eq!(last.phase, ClonePhase::CheckingOut);
+        assert_eq!(last.done, last.total);
+        assert!(clone_events.iter().all(|e| e.url == remote.url));
+    }
+
+    #[test]
+    fn failed_clones_leave_nothing_behind() {
+        let app_dir = TestAppDir::new();
+        let remote = remote();
+        let missing = format!("file://{}", remote.dir.path().join("missing.git").display());
+
+        assert!(clone_git_repo(&missing, None, &NoopSink).is_err());
+        let temp_repos = app_dir.dir.path().join(TEMP_REPOS_DIR);
+        assert_eq!(fs::read_dir(temp_repos).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn branches_are_checked_out_and_recorded() {
+        let _app_dir = TestAppDir::new();
+        let remote = remote();
+
+        let target = clone_git_repo(&remote.url, None, &NoopSink).unwrap();
+        let source = read_source_info(&target, "");
+        assert_eq!(source.commit, Some(remote.two.to_string()));
+        assert_eq!(source.git_ref.as_deref(), Some("main"));
+        assert_eq!(source.remote_url, Some(remote.url.clone()));
+
+        let target = clone_git_repo(&remote.url, Some("feature/x"), &NoopSink).unwrap();
+        assert_eq!(read(&target.join("a.txt")), "one");
+        let source = read_source_info(&target, "");
+        assert_eq!(source.commit, Some(remote.one.to_string()));
+        assert_eq!(source.git_ref.as_deref(), Some("feature/x"));
+    }
+
+    #[test]
+    fn tags_and_commits_are_checked_out_detached() {
+        let _app_dir = TestAppDir::new();
+        let remote = remote();
+
+        let target = clone_git_repo(&remote.url, Some("v1"), &NoopSink).unwrap();
+        assert_eq!(read(&target.join("a.txt")), "one");
+        let source = read_source_info(&target, "");
+        assert_eq!(source.git_ref.as_deref(), Some("v1"));
+        assert!(Repository::open(&target).unwrap().head_detached().unwrap());
+
+        // No ref points at `root`, so it has to be found in the full history.
+        let short = remote.root.to_string()[..7].to_string();
+        let target = clone_git_repo(&remote.url, Some(&short), &NoopSink).unwrap();
+        assert_eq!(read(&target.join("a.txt")), "root");
+        let source = read_source_info(&target, "");
+        assert_eq!(source.commit, Some(remote.root.to_string()));
+    }
+
+    #[test]
+    fn unknown_refs_are_errors() {
+        let _app_dir = TestAppDir::new();
+        let remote = remote();
+        let err = clone_git_repo(&remote.url, Some("nope"), &NoopSink).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no branch, tag or commit named 'nope'"));
+    }
+
+    #[test]
+    fn branches_win_over_tags_of_the_same_name() {
+        let heads = vec![
+            ("refs/heads/v1".to_string(), Oid::zero()),
+            ("refs/tags/v1".to_string(), Oid::zero()),
+        ];
+        let resolved = |name| resolve_ref(Some(name), &heads, None).unwrap();
+
+        assert!(matches!(resolved("v1"), ResolvedRef::Branch(name) if name == "v1"));
+        assert!(matches!(resolved("refs/tags/v1"), ResolvedRef::Tag(name) if name == "v1"));
+        assert!(matches!(resolved("ABC1234"), ResolvedRef::Commit(sha) if sha == "abc1234"));
+        assert!(resolve_ref(None, &heads, None).is_err());
+    }
+
+    #[test]
+    fn tree_urls_check_out_only_their_path() {
+        let remote = remote();
+        let (target, subpath) = checkout(&remote, Some("feature/x/docs"), None, "tree").unwrap();
+
+        assert_eq!(subpath.as_deref(), Some("docs"));
+        assert_eq!(read(&target.join("docs/guide.md")), "guide");
+        assert!(!target.join("a.txt").exists());
+        let source = read_source_info(&target, "");
+        assert_eq!(source.commit, Some(remote.one.to_string()));
+        assert_eq!(source.git_ref.as_deref(), Some("feature/x"));
+    }
+
+    #[test]
+    fn missing_paths_are_errors() {
+        let remote = remote();
+        let err = checkout(&remote, Some("main/missing"), None, "path").unwrap_err();
+        assert!(err.message().contains("'missing' does not exist"));
+    }
+
+    #[test]
+    fn default_branch_is_checked_out_from_the_mirror() {
+        let remote = remote();
+        let (target, subpath) = checkout(&remote, None, None, "default").unwrap();
+
+        assert_eq!(subpath, None);
+        assert_eq!(read(&target.join("a.txt")), "two");
+        let alternates = read(&target.join(".git/objects/info/alternates"));
+        assert!(alternates.contains("mirror.git"));
+
+        let source = read_source_info(&target, "");
+        assert_eq!(source.commit, Some(remote.two.to_string()));
+        assert_eq!(source.git_ref.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn reused_mirror_fetches_new_commits() {
+        let remote = remote();
+        checkout(&remote, None, None, "first").unwrap();
+
+        let repo = Repository::open_bare(remote.dir.path().join("remote.git")).unwrap();
+        let three = commit(
+            &repo,
+            "refs/heads/main",
+            &[("a.txt", "three")],
+            &[remote.two],
+        );
+        let (target, _) = checkout(&remote, None, None, "second").unwrap();
+
+        assert_eq!(read(&target.join("a.txt")), "three");
+        assert_eq!(
+            read_source_info(&target, "").commit,
+            Some(three.to_string())
+        );
+    }
+
+    #[test]
+    fn ref_paths_split_at_the_longest_advertised_ref() {
+        let heads = vec![
+            ("refs/heads/main".to_string(), Oid::zero()),
+            ("refs/heads/feature/x".to_string(), Oid::zero()),
+            ("refs/tags/v1".to_string(), Oid::zero()),
+        ];
+        let split = |ref_path| split_ref_path(ref_path, &heads);
+
+        assert_eq!(
+            split("feature/x/src/lib.rs"),
+            (
+                Some("feature/x".to_string()),
+                Some("src/lib.rs".to_string())
+            )
+        );
+        assert_eq!(split("v1"), (Some("v1".to_string()), None));
+        assert_eq!(
+            split("abc1234/docs"),
+            (Some("abc1234".to_string()), Some("docs".to_string()))
+        );
+    }
+
+    #[test]
+    fn repo_names_come_from_the_last_url_segment() {
+        assert_eq!(
+            repo_name_from_url("https://github.com/owner/repo.git"),
+            "repo"
+        );
+        assert_eq!(
+            repo_name_from_url("git@github.com:owner/my-repo/"),
+            "my-repo"
+        );
+        assert_eq!(repo_name_from_url("git@host:repo"), "repo");
+        assert_eq!(repo_name_from_url("https://host/.."), "repo");
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::Path;
+
+// Language names double as Markdown code fence tags, so they follow the
+// identifiers highlight.js / GitHub understand.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("ts", "typescript"),
+    ("mts", "typescript"),
+    ("cts", "typescript"),
+    ("tsx", "tsx"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "jsx"),
+    ("py", "python"),
+    ("pyi", "python"),
+    ("go", "go"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("cxx", "cpp"),
+    ("hh", "cpp"),
+    ("hpp", "cpp"),
+    ("hxx", "cpp"),
+    ("cs", "csharp"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("scala", "scala"),
+    ("swift", "swift"),
+    ("m", "objectivec"),
+    ("mm", "objectivec"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("pl", "perl"),
+    ("lua", "lua"),
+    ("dart", "dart"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("hs", "haskell"),
+    ("ml", "ocaml"),
+    ("clj", "clojure"),
+    ("zig", "zig"),
+    ("nim", "nim"),
+    ("r", "r"),
+    ("jl", "julia"),
+    ("sh", "bash"),
+    ("bash", "bash"),
+    ("zsh", "bash"),
+    ("fish", "fish"),
+    ("ps1", "powershell"),
+    ("bat", "batch"),
+    ("cmd", "batch"),
+    ("sql", "sql"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("xml", "xml"),
+    ("svg", "xml"),
+    ("vue", "vue"),
+    ("svelte", "svelte"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("sass", "sass"),
+    ("less", "less"),
+    ("json", "json"),
+    ("jsonc", "json"),
+    ("toml", "toml"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("ini", "ini"),
+    ("cfg", "ini"),
+    ("md", "markdown"),
+    ("markdown", "markdown"),
+    ("rst", "rst"),
+    ("tex", "latex"),
+    ("proto", "protobuf"),
+    ("graphql", "graphql"),
+    ("gql", "graphql"),
+    ("tf", "hcl"),
+    ("hcl", "hcl"),
+    ("nix", "nix"),
+    ("gradle", "groovy"),
+    ("groovy", "groovy"),
+    ("txt", "text"),
+];
+
+const FILENAMES: &[(&str, &str)] = &[
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("Makefile", "makefile"),
+    ("GNUmakefile", "makefile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Cargo.lock", "toml"),
+    ("Gemfile", "ruby"),
+    ("Rakefile", "ruby"),
+    ("Justfile", "makefile"),
+    (".bashrc", "bash"),
+    (".zshrc", "bash"),
+];
+
+/// Best guess at the language of a file from its name and extension.
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+
+    if let Some((_, language)) = FILENAMES.iter().find(|(name, _)| *name == file_name) {
+        return Some(language);
+    }
+
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
@@ -1,7 +1,31 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+pub mod cache;
+pub mod cancel;
+pub mod chunks;
 pub mod commands;
+pub mod credentials;
+pub mod encoding;
+pub mod engine;
 pub mod error;
+pub mod forge;
+pub mod git;
+pub mod language;
+pub mod output;
+pub mod profiles;
+pub mod progress;
+pub mod report;
+pub mod skeleton;
+pub mod summary;
+pub mod tokenizer;
+pub mod transform;
 pub mod utils;
+pub mod walk;
+
+pub use cancel::{CancelToken, ParseRegistry};
+pub use progress::{
+    ChannelSink, NoopSink, ParseEvent, ParsePhase, ParseProgress, ProgressSink, TauriEventSink,
+};
+pub use utils::{parse_files, ParseMetadata, ParseOptions};
 
 #[cfg(target_os = "windows")]
 use tauri::Manager;
@@ -18,6 +42,7 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_log::Builder::new().build())
+        .manage(cancel::ParseRegistry::default())
         .setup(|app| {
             utils::setup_tray(app)?;
 
@@ -34,17 +59,31 @@ pub fn run() {
             commands::get_preview_tree,
             commands::get_parsed_preview_tree,
             commands::parse,
+            commands::cancel_parse,
             commands::parse_repository,
             commands::get_files,
             commands::get_file_content,
             commands::get_file_metadata,
+            commands::get_parse_report,
+            commands::get_chunk_content,
+            commands::export_parse_jsonl,
             commands::update_file,
             commands::rename_file,
             commands::delete_file,
             commands::open_in_default_editor,
+            commands::open_chunk_in_default_editor,
             commands::open_in_folder,
             commands::expand_folder,
             commands::expand_parsed_folder,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::update_profile,
+            commands::delete_profile,
+            commands::list_credentials,
+            commands::set_credential,
+            commands::delete_credential,
+            commands::list_repo_cache,
+            commands::clear_repo_cache,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,882 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::chunks::{ChunkInfo, ChunkWriter};
+use crate::language;
+use crate::summary::{self, SourceInfo, WrittenFile};
+use crate::tokenizer::{self, TokenizerKind};
+use crate::utils::{ParseOptions, DEFAULT_HEADER_TEMPLATE};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Plain,
+    Markdown,
+    Xml,
+}
+
+/// Renders parsed files into `content.txt`. `index` is the 1-based position
+/// of the document in the output, `path` is the path shown to the reader and
+/// `source` the file on disk it was read from.
+///
+/// `write_file` returns where in the bytes it wrote the content itself lies,
+/// so the output can be split back into files without looking for headers.
+pub trait FileWriter: Send {
+    /// Text placed above the files, right after the prologue, such as the
+    /// parse summary.
+    fn write_preamble(&mut self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "{}", text)
+    }
+
+    fn write_prologue(&mut self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_file(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        path: &str,
+        source: &Path,
+        content: &str,
+    ) -> io::Result<Range<usize>>;
+
+    fn write_epilogue(&mut self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn writer_for(options: &ParseOptions) -> Box<dyn FileWriter> {
+    match options.format {
+        OutputFormat::Plain => Box::new(PlainWriter {
+            header_template: options
+                .header_template
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HEADER_TEMPLATE.to_string()),
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownWriter),
+        OutputFormat::Xml => Box::new(XmlWriter),
+    }
+}
+
+/// One line of `content.jsonl`. `content` is the file as it went into the
+/// output, after comment stripping, skeletons or truncation, so the size,
+/// line count and hash describe that text rather than the file on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub language: Option<String>,
+    /// Length of `content` in bytes.
+    #[serde(alias = "size")]
+    pub content_size: u64,
+    pub lines: usize,
+    /// Hash of `content`, taken before it is escaped for the XML format.
+    /// Records cut back out of an XML `content.txt` hash the unescaped text,
+    /// in which control characters XML cannot hold are U+FFFD.
+    pub sha256: String,
+    pub content: String,
+}
+
+impl FileRecord {
+    pub fn new(path: &str, source: &Path, content: &str) -> Self {
+        FileRecord {
+            path: path.to_string(),
+            language: language::detect_language(source).map(str::to_string),
+            content_size: content.len() as u64,
+            lines: content.lines().count(),
+            sha256: format!("{:x}", Sha256::digest(content.as_bytes())),
+            content: content.to_string(),
+        }
+    }
+}
+
+/// Streams files into `content.txt`. When a summary is requested the files
+/// are staged in a side file first, because the summary at the top of the
+/// output is only known once every file has been written.
+pub struct ContentOutput {
+    path: PathBuf,
+    staging_path: Option<PathBuf>,
+    file: BufWriter<File>,
+    writer: Box<dyn FileWriter>,
+    jsonl: Option<BufWriter<File>>,
+    chunks: Option<ChunkWriter>,
+    tokenizer: TokenizerKind,
+    written: Vec<WrittenFile>,
+    framing_tokens: u64,
+    /// Bytes written to `file` so far.
+    offset: u64,
+    buffer: Vec<u8>,
+}
+
+/// A file waiting to be written by `ContentOutput::write_files`.
+pub struct PendingFile {
+    pub path: String,
+    pub source: PathBuf,
+    pub content: String,
+}
+
+pub struct OutputStats {
+    pub files: Vec<WrittenFile>,
+    pub total_tokens: u64,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+impl ContentOutput {
+    pub fn new(path: &Path, options: &ParseOptions) -> io::Result<Self> {
+        let staging_path = options
+            .include_summary
+            .then(|| path.with_extension("txt.part"));
+        let file = File::create(staging_path.as_deref().unwrap_or(path))?;
+
+        let mut output = ContentOutput {
+            path: path.to_path_buf(),
+            staging_path,
+            file: BufWriter::new(file),
+            writer: writer_for(options),
+            jsonl: None,
+            chunks: None,
+            tokenizer: options.tokenizer,
+            written: Vec::new(),
+            framing_tokens: 0,
+            offset: 0,
+            buffer: Vec::new(),
+        };
+        if output.staging_path.is_none() {
+            let mut prologue = Vec::new();
+            output.writer.write_prologue(&mut prologue)?;
+            output.framing_tokens += count_tokens(output.tokenizer, &prologue);
+            output.file.write_all(&prologue)?;
+            output.offset += prologue.len() as u64;
+        }
+        Ok(output)
+    }
+
+    pub fn with_jsonl(mut self, file: File) -> Self {
+        self.jsonl = Some(BufWriter::new(file));
+        self
+    }
+
+    pub fn with_chunks(mut self, chunks: ChunkWriter) -> Self {
+        self.chunks = Some(chunks);
+        self
+    }
+
+    /// Writes one file and returns the number of tokens it takes up in the
+    /// output, header included.
+    pub fn write_file(&mut self, path: &str, source: &Path, content: &str) -> io::Result<u64> {
+        self.buffer.clear();
+        let index = self.written.len() + 1;
+        let range = self
+            .writer
+            .write_file(&mut self.buffer, index, path, source, content)?;
+        self.file.write_all(&self.buffer)?;
+
+        let tokens = count_tokens(self.tokenizer, &self.buffer);
+        let block_len = self.buffer.len();
+        self.record(path, source, content, tokens, range, block_len)?;
+        Ok(tokens)
+    }
+
+    /// Same as calling `write_file` for each file in order, except that the
+    /// rendered files are tokenized in parallel.
+    pub fn write_files(&mut self, files: &[PendingFile]) -> io::Result<()> {
+        let mut blocks = Vec::with_capacity(files.len());
+        for (i, file) in files.iter().enumerate() {
+            let index = self.written.len() + i + 1;
+            let mut block = Vec::new();
+            let range = self.writer.write_file(
+                &mut block,
+                index,
+                &file.path,
+                &file.source,
+                &file.content,
+            )?;
+            blocks.push((block, range));
+        }
+
+        let tokenizer = self.tokenizer;
+        let tokens: Vec<u64> = blocks
+            .par_iter()
+            .map(|(block, _)| count_tokens(tokenizer, block))
+            .collect();
+
+        for ((file, (block, range)), tokens) in files.iter().zip(blocks).zip(tokens) {
+            self.file.write_all(&block)?;
+            let (path, source, content) = (&file.path, &file.source, &file.content);
+            self.record(path, source, content, tokens, range, block.len())?;
+        }
+        Ok(())
+    }
+
+    /// `range` is where the content lies in the file's rendered block of
+    /// `block_len` bytes.
+    fn record(
+        &mut self,
+        path: &str,
+        source: &Path,
+        content: &str,
+        tokens: u64,
+        range: Range<usize>,
+        block_len: usize,
+    ) -> io::Result<()> {
+        self.written.push(WrittenFile {
+            path: path.to_string(),
+            size: content.len() as u64,
+            tokens,
+            language: language::detect_language(source),
+            content_range: self.offset + range.start as u64..self.offset + range.end as u64,
+        });
+        self.offset += block_len as u64;
+
+        if let Some(jsonl) = &mut self.jsonl {
+            write_record(jsonl, &FileRecord::new(path, source, content))?;
+        }
+        if let Some(chunks) = &mut self.chunks {
+            chunks.write_file(path, source, content, tokens)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self, source: &SourceInfo) -> io::Result<OutputStats> {
+        if let Some(jsonl) = &mut self.jsonl {
+            jsonl.flush()?;
+        }
+        let chunks = match self.chunks.take() {
+            Some(chunks) => chunks.finish()?,
+            None => Vec::new(),
+        };
+
+        let mut epilogue = Vec::new();
+        self.writer.write_epilogue(&mut epilogue)?;
+        self.framing_tokens += count_tokens(self.tokenizer, &epilogue);
+
+        match self.staging_path.take() {
+            None => {
+                self.file.write_all(&epilogue)?;
+                self.file.flush()?;
+            }
+            Some(staging_path) => {
+                self.file.flush()?;
+
+                let mut head = Vec::new();
+                let summary = summary::render_summary(&self.written, source);
+                self.writer.write_prologue(&mut head)?;
+                self.writer.write_preamble(&mut head, &summary)?;
+                self.framing_tokens += count_tokens(self.tokenizer, &head);
+                // The files move down by the head written above them.
+                let shift = head.len() as u64;
+                for file in &mut self.written {
+                    file.content_range =
+                        file.content_range.start + shift..file.content_range.end + shift;
+                }
+
+                let mut out = BufWriter::new(File::create(&self.path)?);
+                out.write_all(&head)?;
+                io::copy(&mut File::open(&staging_path)?, &mut out)?;
+                out.write_all(&epilogue)?;
+                out.flush()?;
+
+                fs::remove_file(staging_path)?;
+            }
+        }
+
+        let file_tokens: u64 = self.written.iter().map(|f| f.tokens).sum();
+        Ok(OutputStats {
+            total_tokens: file_tokens + self.framing_tokens,
+            files: self.written,
+            chunks,
+        })
+    }
+}
+
+pub fn count_tokens(kind: TokenizerKind, rendered: &[u8]) -> u64 {
+    tokenizer::count_tokens(kind, &String::from_utf8_lossy(rendered))
+}
+
+pub fn write_record(out: &mut dyn Write, record: &FileRecord) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    writeln!(out)
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Reading Existing Output
+// /////////////////////////////////////////////////////////////////////////////
+
+/// Cuts the files out of a previously written `content.txt` at the content
+/// ranges recorded while writing it, in output order. Returns `None` when a
+/// range does not fit the content, e.g. after it was edited.
+pub fn split_content_at(
+    content: &str,
+    format: OutputFormat,
+    files: &[(String, Range<u64>)],
+) -> Option<Vec<FileRecord>> {
+    let mut files: Vec<&(String, Range<u64>)> = files.iter().collect();
+    files.sort_by_key(|(_, range)| range.start);
+
+    files
+        .into_iter()
+        .map(|(path, range)| {
+            let range = usize::try_from(range.start).ok()?..usize::try_from(range.end).ok()?;
+            let written = content.get(range)?;
+            let block = match format {
+                OutputFormat::Xml => unescape_xml(written),
+                OutputFormat::Plain | OutputFormat::Markdown => written.to_string(),
+            };
+            Some(FileRecord::new(path, Path::new(path), &block))
+        })
+        .collect()
+}
+
+/// Splits a previously written `content.txt` back into per-file records by
+/// its headers, for parses made before content ranges were recorded. Only
+/// headers naming one of `known_paths` start a new file, but a file whose
+/// content contains another file's header line is still split there, and
+/// the Markdown and XML formats cannot tell whether a file ended in a
+/// newline.
+pub fn split_content(
+    content: &str,
+    format: OutputFormat,
+    header_template: Option<&str>,
+    known_paths: &HashSet<String>,
+) -> Vec<FileRecord> {
+    let header_template = header_template.unwrap_or(DEFAULT_HEADER_TEMPLATE);
+    let plain_headers: HashMap<String, &str> = match format {
+        OutputFormat::Plain => known_paths
+            .iter()
+            .map(|path| (header_template.replace("{path}", path), path.as_str()))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut records = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_end_matches(['\r', '\n']);
+        let parsed = match format {
+            OutputFormat::Plain => plain_headers
+                .get(line)
+                .map(|path| read_plain_block(&lines, i + 1, &plain_headers, path)),
+            OutputFormat::Markdown => markdown_heading_path(line)
+                .filter(|path| known_paths.contains(*path))
+                .and_then(|path| read_markdown_block(&lines, i + 1, path)),
+            OutputFormat::Xml => xml_document_start(line)
+                .then(|| read_xml_block(&lines, i + 1))
+                .flatten(),
+        };
+
+        match parsed {
+            Some((path, block, next)) => {
+                records.push(FileRecord::new(&path, Path::new(&path), &block));
+                i = next;
+            }
+            None => i += 1,
+        }
+    }
+    records
+}
+
+fn read_plain_block(
+    lines: &[&str],
+    start: usize,
+    headers: &HashMap<String, &str>,
+    path: &str,
+) -> (String, String, usize) {
+    let end = (start..lines.len())
+        .find(|&j| headers.contains_key(lines[j].trim_end_matches(['\r', '\n'])))
+        .unwrap_or(lines.len());
+
+    let mut block: String = lines[start..end].concat();
+    // The writer appends one newline after each file's content.
+    if block.ends_with('\n') {
+        block.pop();
+    }
+    (path.to_string(), block, end)
+}
+
+fn markdown_heading_path(line: &str) -> Option<&str> {
+    let heading = line.strip_prefix("## ")?;
+    Some(
+        heading
+            .strip_prefix('`')
+            .and_then(|h| h.strip_suffix('`'))
+            .unwrap_or(heading),
+    )
+}
+
+fn read_markdown_block(
+    lines: &[&str],
+    start: usize,
+    path: &str,
+) -> Option<(String, String, usize)> {
+    let fence_line = lines.get(start + 1)?.trim_end_matches(['\r', '\n']);
+    let fence_len = fence_line.chars().take_while(|c| *c == '`').count();
+    if fence_len < 3 {
+        return None;
+    }
+    let fence = &fence_line[..fence_len];
+
+    let content_start = start + 2;
+    let end =
+        (content_start..lines.len()).find(|&j| lines[j].trim_end_matches(['\r', '\n']) == fence)?;
+
+    Some((
+        path.to_string(),
+        lines[content_start..end].concat(),
+        end + 1,
+    ))
+}
+
+fn xml_document_start(line: &str) -> bool {
+    line.starts_with("<document index=\"") && line.ends_with("\">")
+}
+
+fn read_xml_block(lines: &[&str], start: usize) -> Option<(String, String, usize)> {
+    let source = lines
+        .get(start)?
+        .trim_end_matches(['\r', '\n'])
+        .strip_prefix("<source>")?
+        .strip_suffix("</source>")?;
+    if lines.get(start + 1)?.trim_end_matches(['\r', '\n']) != "<document_content>" {
+        return None;
+    }
+
+    let content_start = start + 2;
+    let end = (content_start..lines.len())
+        .find(|&j| lines[j].trim_end_matches(['\r', '\n']) == "</document_content>")?;
+
+    Some((
+        unescape_xml(source),
+        unescape_xml(&lines[content_start..end].concat()),
+        end + 1,
+    ))
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Writers
+// /////////////////////////////////////////////////////////////////////////////
+
+pub struct PlainWriter {
+    header_template: String,
+}
+
+impl FileWriter for PlainWriter {
+    fn write_file(
+        &mut self,
+        out: &mut dyn Write,
+        _index: usize,
+        path: &str,
+        _source: &Path,
+        content: &str,
+    ) -> io::Result<Range<usize>> {
+        let header = self.header_template.replace("{path}", path);
+        writeln!(out, "{}", header)?;
+        out.write_all(content.as_bytes())?;
+        writeln!(out)?;
+
+        let start = header.len() + 1;
+        Ok(start..start + content.len())
+    }
+}
+
+pub struct MarkdownWriter;
+
+impl FileWriter for MarkdownWriter {
+    fn write_preamble(&mut self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        let fence = "`".repeat(fence_length(text));
+        writeln!(out, "{}text\n{}{}\n", fence, text, fence)
+    }
+
+    fn write_file(
+        &mut self,
+        out: &mut dyn Write,
+        _index: usize,
+        path: &str,
+        source: &Path,
+        content: &str,
+    ) -> io::Result<Range<usize>> {
+        let heading = if path.contains('`') {
+            format!("## {}", path)
+        } else {
+            format!("## `{}`", path)
+        };
+        let fence = "`".repeat(fence_length(content));
+        let language = language::detect_language(source).unwrap_or("");
+        let head = format!("{}\n\n{}{}\n", heading, fence, language);
+
+        out.write_all(head.as_bytes())?;
+        out.write_all(content.as_bytes())?;
+        if !content.is_empty() && !content.ends_with('\n') {
+            writeln!(out)?;
+        }
+        writeln!(out, "{}", fence)?;
+        writeln!(out)?;
+
+        Ok(head.len()..head.len() + content.len())
+    }
+}
+
+/// Wraps every file in a `<document>` element inside a `<documents>` root,
+/// the layout recommended for long-context prompts. The summary goes into
+/// the root as well, so the output stays a single well-formed document.
+pub struct XmlWriter;
+
+impl FileWriter for XmlWriter {
+    fn write_preamble(&mut self, out: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(out, "<summary>\n{}</summary>", escape_xml(text))
+    }
+
+    fn write_prologue(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "<documents>")
+    }
+
+    fn write_file(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        path: &str,
+        _source: &Path,
+        content: &str,
+    ) -> io::Result<Range<usize>> {
+        let head = format!(
+            "<document index=\"{}\">\n<source>{}</source>\n<document_content>\n",
+            index,
+            escape_xml(path)
+        );
+        let escaped = escape_xml(content);
+
+        out.write_all(head.as_bytes())?;
+        out.write_all(escaped.as_bytes())?;
+        if !content.is_empty() && !content.ends_with('\n') {
+            writeln!(out)?;
+        }
+        writeln!(out, "</document_content>")?;
+        writeln!(out, "</document>")?;
+
+        Ok(head.len()..head.len() + escaped.len())
+    }
+
+    fn write_epilogue(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "</documents>")
+    }
+}
+
+/// Escapes markup characters. Control characters that XML 1.0 cannot
+/// represent at all are replaced with U+FFFD.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {
+                escaped.push(char::REPLACEMENT_CHARACTER)
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A fence has to be longer than any backtick run inside the block,
+/// and at least three characters long.
+fn fence_length(content: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in content.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    (longest + 1).max(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn markdown(path: &str, content: &str) -> String {
+        let mut out = Vec::new();
+        MarkdownWriter
+            .write_file(&mut out, 1, path, Path::new(path), content)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn fences_are_at_least_three_backticks() {
+        assert_eq!(fence_length(""), 3);
+        assert_eq!(fence_length("let a = `b`;"), 3);
+        assert_eq!(fence_length("``"), 3);
+    }
+
+    #[test]
+    fn fences_outgrow_backtick_runs_in_the_content() {
+        assert_eq!(fence_length("```rust\nfn main() {}\n```"), 4);
+        assert_eq!(fence_length("````\n`` ` ```"), 5);
+        assert_eq!(fence_length("a``````b"), 7);
+    }
+
+    #[test]
+    fn markdown_blocks_are_tagged_with_the_language() {
+        assert_eq!(
+            markdown("src/main.rs", "fn main() {}"),
+            "## `src/main.rs`\n\n```rust\nfn main() {}\n```\n\n"
+        );
+    }
+
+    #[test]
+    fn markdown_blocks_survive_nested_fences() {
+        let content = "# Title\n\n```sh\nls\n```\n";
+        let rendered = markdown("README.md", content);
+        assert!(rendered.starts_with("## `README.md`\n\n````markdown\n"));
+        assert!(rendered.ends_with("```\n````\n\n"));
+    }
+
+    #[test]
+    fn plain_headers_use_the_template() {
+        let mut writer = PlainWriter {
+            header_template: "--- {path} ---".to_string(),
+        };
+        let mut out = Vec::new();
+        writer
+            .write_file(&mut out, 1, "a.txt", Path::new("a.txt"), "hello")
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "--- a.txt ---\nhello\n");
+    }
+
+    #[test]
+    fn xml_documents_are_numbered_and_escaped() {
+        let mut writer = XmlWriter;
+        let mut out = Vec::new();
+        writer.write_prologue(&mut out).unwrap();
+        writer
+            .write_file(&mut out, 1, "a&b.rs", Path::new("a&b.rs"), "if a < b {}")
+            .unwrap();
+        writer
+            .write_file(&mut out, 2, "c.rs", Path::new("c.rs"), "x\n")
+            .unwrap();
+        writer.write_epilogue(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<documents>\n\
+             <document index=\"1\">\n<source>a&amp;b.rs</source>\n\
+             <document_content>\nif a &lt; b {}\n</document_content>\n</document>\n\
+             <document index=\"2\">\n<source>c.rs</source>\n\
+             <document_content>\nx\n</document_content>\n</document>\n\
+             </documents>\n"
+        );
+    }
+
+    #[test]
+    fn xml_escaping_replaces_unrepresentable_characters() {
+        assert_eq!(escape_xml("a\tb\r\n<c>"), "a\tb\r\n&lt;c&gt;");
+        assert_eq!(escape_xml("\u{0}\u{1b}[0m"), "\u{FFFD}\u{FFFD}[0m");
+    }
+
+    fn render(options: &ParseOptions, files: &[(&str, &str)]) -> String {
+        let mut writer = writer_for(options);
+        let mut out = Vec::new();
+        writer.write_prologue(&mut out).unwrap();
+        for (i, (path, content)) in files.iter().enumerate() {
+            writer
+                .write_file(&mut out, i + 1, path, Path::new(path), content)
+                .unwrap();
+        }
+        writer.write_epilogue(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn records_describe_the_content() {
+        let record = FileRecord::new("src/lib.rs", Path::new("src/lib.rs"), "a\nb\n");
+        assert_eq!(record.language.as_deref(), Some("rust"));
+        assert_eq!(record.content_size, 4);
+        assert_eq!(record.lines, 2);
+        assert_eq!(
+            record.sha256,
+            format!("{:x}", Sha256::digest("a\nb\n".as_bytes()))
+        );
+
+        let mut out = Vec::new();
+        write_record(&mut out, &record).unwrap();
+        let line = String::from_utf8(out).unwrap();
+        assert!(line.ends_with("}\n") && line.matches('\n').count() == 1);
+    }
+
+    #[test]
+    fn written_content_splits_back_into_files() {
+        let files = [
+            ("src/a.rs", "fn a() {}\n"),
+            ("notes.md", "```\n## `src/a.rs`\n```\nno newline"),
+        ];
+        let known: HashSet<String> = files.iter().map(|(p, _)| p.to_string()).collect();
+
+        for format in [
+            OutputFormat::Plain,
+            OutputFormat::Markdown,
+            OutputFormat::Xml,
+        ] {
+            let options = ParseOptions {
+                format,
+                ..Default::default()
+            };
+            let records = split_content(&render(&options, &files), format, None, &known);
+            let paths: Vec<&str> = records.iter().map(|r| r.path.as_str()).collect();
+            assert_eq!(paths, ["src/a.rs", "notes.md"], "{:?}", format);
+            assert_eq!(records[0].content.trim_end(), "fn a() {}", "{:?}", format);
+        }
+    }
+
+    fn options(format: OutputFormat) -> ParseOptions {
+        ParseOptions {
+            format,
+            ..ParseOptions::default()
+        }
+    }
+
+    fn write_output(options: &ParseOptions, files: &[(&str, &str)]) -> String {
+        write_output_with_stats(options, files).0
+    }
+
+    fn write_output_with_stats(
+        options: &ParseOptions,
+        files: &[(&str, &str)],
+    ) -> (String, OutputStats) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("content.txt");
+        let mut output = ContentOutput::new(&path, options).unwrap();
+        for (name, content) in files {
+            output.write_file(name, Path::new(name), content).unwrap();
+        }
+        let stats = output.finish(&SourceInfo::default()).unwrap();
+        (fs::read_to_string(path).unwrap(), stats)
+    }
+
+    /// Contents that trip up splitting by headers: one contains another
+    /// file's header, one does not end in a newline.
+    const TRICKY_FILES: [(&str, &str); 3] = [
+        (
+            "a.rs",
+            "x\n===== b.md =====\n## `b.md`\n<document index=\"2\">\ny\n",
+        ),
+        ("b.md", "# B\n"),
+        ("keep.log", "keep"),
+    ];
+
+    fn split_written(options: &ParseOptions) -> Vec<FileRecord> {
+        let (content, stats) = write_output_with_stats(options, &TRICKY_FILES);
+        let ranges: Vec<(String, Range<u64>)> = stats
+            .files
+            .iter()
+            .map(|file| (file.path.clone(), file.content_range.clone()))
+            .collect();
+        split_content_at(&content, options.format, &ranges).unwrap()
+    }
+
+    #[test]
+    fn split_content_at_restores_every_file_exactly() {
+        for format in [
+            OutputFormat::Plain,
+            OutputFormat::Markdown,
+            OutputFormat::Xml,
+        ] {
+            for include_summary in [false, true] {
+                let options = ParseOptions {
+                    include_summary,
+                    ..options(format)
+                };
+                let records = split_written(&options);
+
+                assert_eq!(records.len(), TRICKY_FILES.len(), "{:?}", format);
+                for (record, (path, content)) in records.iter().zip(TRICKY_FILES) {
+                    assert_eq!(record.path, path);
+                    assert_eq!(record.content, content, "{:?} {}", format, path);
+                    assert_eq!(record.content_size, content.len() as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn xml_records_hash_the_text_xml_could_hold() {
+        let content = "a\u{1}b\n";
+        let options = options(OutputFormat::Xml);
+        let (written, stats) = write_output_with_stats(&options, &[("a.rs", content)]);
+        let ranges = vec![("a.rs".to_string(), stats.files[0].content_range.clone())];
+        let records = split_content_at(&written, OutputFormat::Xml, &ranges).unwrap();
+
+        let original = FileRecord::new("a.rs", Path::new("a.rs"), content);
+        assert_eq!(records[0].content, "a\u{FFFD}b\n");
+        assert_ne!(records[0].sha256, original.sha256);
+        let replaced = FileRecord::new("a.rs", Path::new("a.rs"), "a\u{FFFD}b\n");
+        assert_eq!(records[0].sha256, replaced.sha256);
+    }
+
+    #[test]
+    fn records_read_the_size_field_of_older_exports() {
+        let line =
+            r#"{"path":"a.rs","language":"rust","size":2,"lines":1,"sha256":"","content":"a\n"}"#;
+        let record: FileRecord = serde_json::from_str(line).unwrap();
+        assert_eq!(record.content_size, 2);
+    }
+
+    #[test]
+    fn split_content_at_rejects_ranges_past_the_end() {
+        let ranges = vec![("a.rs".to_string(), 0..100)];
+        assert!(split_content_at("short", OutputFormat::Plain, &ranges).is_none());
+    }
+
+    #[test]
+    fn split_content_falls_back_to_known_headers() {
+        let content = write_output(
+            &options(OutputFormat::Plain),
+            &[("a.rs", "a\n"), ("b.rs", "b")],
+        );
+        let known_paths: HashSet<String> = ["a.rs", "b.rs"].map(str::to_string).into();
+        let records = split_content(&content, OutputFormat::Plain, None, &known_paths);
+
+        let contents: Vec<&str> = records.iter().map(|r| r.content.as_str()).collect();
+        assert_eq!(contents, ["a\n", "b"]);
+    }
+
+    #[test]
+    fn xml_summary_is_inside_the_root_element() {
+        let options = ParseOptions {
+            include_summary: true,
+            ..options(OutputFormat::Xml)
+        };
+        let content = write_output(
+            &options,
+            &[("a.rs", "fn a() {}\n"), ("b.rs", "fn b() {}\n")],
+        );
+
+        assert!(content.starts_with("<documents>\n<summary>\n"));
+        assert!(content.ends_with("</document>\n</documents>\n"));
+        assert_eq!(content.matches("<documents>").count(), 1);
+        assert!(content.contains("<document index=\"1\">\n<source>a.rs</source>"));
+        assert!(content.contains("<document index=\"2\">\n<source>b.rs</source>"));
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{self, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io, path::PathBuf};
+use uuid::Uuid;
+
+use crate::utils::{self, ParseOptions, StoreLock};
+
+pub const PROFILES_FILENAME: &str = "profiles.json";
+
+static PROFILES_LOCK: StoreLock = StoreLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseProfile {
+    pub id: String,
+    pub name: String,
+    pub options: ParseOptions,
+    pub created_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+}
+
+pub fn get_profiles_path() -> Result<PathBuf> {
+    Ok(utils::get_app_dir()?.join(PROFILES_FILENAME))
+}
+
+pub fn list_profiles() -> Result<Vec<ParseProfile>> {
+    let _guard = PROFILES_LOCK.lock();
+    load_profiles()
+}
+
+pub fn get_profile(id: &str) -> Result<ParseProfile> {
+    list_profiles()?
+        .into_iter()
+        .find(|profile| profile.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", id))
+}
+
+/// Options of a parse given either explicit `options` or the id of a
+/// profile to take them from. Giving both is an error rather than a guess at
+/// which one was meant.
+pub fn resolve_options(
+    options: Option<ParseOptions>,
+    profile_id: Option<String>,
+) -> Result<ParseOptions> {
+    match (options, profile_id) {
+        (Some(_), Some(_)) => Err(anyhow::anyhow!(
+            "Pass either parse options or a profile id, not both"
+        )),
+        (Some(options), None) => Ok(options),
+        (None, Some(id)) => Ok(get_profile(&id)?.options),
+        (None, None) => Ok(ParseOptions::default()),
+    }
+}
+
+pub fn create_profile(name: String, options: ParseOptions) -> Result<ParseProfile> {
+    let _guard = PROFILES_LOCK.lock();
+    let name = validate_name(name)?;
+
+    let mut profiles = load_profiles()?;
+    let now = Local::now();
+    let profile = ParseProfile {
+        id: Uuid::new_v4().to_string(),
+        name,
+        options,
+        created_at: now,
+        updated_at: now,
+    };
+    profiles.push(profile.clone());
+    save_profiles(&profiles)?;
+
+    Ok(profile)
+}
+
+pub fn update_profile(
+    id: &str,
+    name: Option<String>,
+    options: Option<ParseOptions>,
+) -> Result<ParseProfile> {
+    let _guard = PROFILES_LOCK.lock();
+
+    let mut profiles = load_profiles()?;
+    let profile = profiles
+        .iter_mut()
+        .find(|profile| profile.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Profile not found: {}", id))?;
+
+    if let Some(name) = name {
+        profile.name = validate_name(name)?;
+    }
+    if let Some(options) = options {
+        profile.options = options;
+    }
+    profile.updated_at = Local::now();
+
+    let updated = profile.clone();
+    save_profiles(&profiles)?;
+
+    Ok(updated)
+}
+
+pub fn delete_profile(id: &str) -> Result<()> {
+    let _guard = PROFILES_LOCK.lock();
+
+    let mut profiles = load_profiles()?;
+    let count = profiles.len();
+    profiles.retain(|profile| profile.id != id);
+    if profiles.len() == count {
+        return Err(anyhow::anyhow!("Profile not found: {}", id));
+    }
+    save_profiles(&profiles)
+}
+
+fn validate_name(name: String) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Profile name cannot be empty"));
+    }
+    Ok(name.to_string())
+}
+
+fn load_profiles() -> Result<Vec<ParseProfile>> {
+    let path = get_profiles_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(io::BufReader::new(file))?)
+}
+
+fn save_profiles(profiles: &[ParseProfile]) -> Result<()> {
+    utils::write_atomic(&get_profiles_path()?, &serde_json::to_vec_pretty(profiles)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TestAppDir;
+    use std::fs;
+
+    fn options(include: &str) -> ParseOptions {
+        ParseOptions {
+            include: vec![include.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profiles_are_created_updated_and_deleted() {
+        let _app_dir = TestAppDir::new();
+
+        let created = create_profile("  Rust only ".to_string(), options("*.rs")).unwrap();
+        assert_eq!(created.name, "Rust only");
+        assert_eq!(get_profile(&created.id).unwrap().options.include, ["*.rs"]);
+
+        let updated = update_profile(&created.id, None, Some(options("*.ts"))).unwrap();
+        assert_eq!(updated.name, "Rust only");
+        assert_eq!(updated.created_at, created.created_at);
+        assert_eq!(list_profiles().unwrap()[0].options.include, ["*.ts"]);
+
+        delete_profile(&created.id).unwrap();
+        assert!(list_profiles().unwrap().is_empty());
+        assert!(get_profile(&created.id).is_err());
+    }
+
+    #[test]
+    fn missing_profiles_and_empty_names_are_errors() {
+        let _app_dir = TestAppDir::new();
+
+        assert!(update_profile("missing", Some("name".to_string()), None).is_err());
+        assert!(delete_profile("missing").is_err());
+        assert!(create_profile("   ".to_string(), ParseOptions::default()).is_err());
+        assert!(list_profiles().unwrap().is_empty());
+    }
+
+    #[test]
+    fn profiles_are_stored_as_a_json_array() {
+        let app_dir = TestAppDir::new();
+        let created = create_profile("Docs".to_string(), options("*.md")).unwrap();
+
+        let path = app_dir.dir.path().join(PROFILES_FILENAME);
+        let stored: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(stored[0]["id"], created.id.as_str());
+        assert_eq!(stored[0]["name"], "Docs");
+        assert_eq!(stored[0]["options"]["include"][0], "*.md");
+        assert!(stored[0]["created_at"].is_string());
+        assert!(!app_dir.dir.path().join("profiles.json.tmp").exists());
+    }
+
+    #[test]
+    fn parse_options_come_from_either_the_options_or_a_profile() {
+        let _app_dir = TestAppDir::new();
+        let profile = create_profile("Rust".to_string(), options("*.rs")).unwrap();
+
+        let resolved = resolve_options(None, Some(profile.id.clone())).unwrap();
+        assert_eq!(resolved.include, ["*.rs"]);
+        let resolved = resolve_options(Some(options("*.ts")), None).unwrap();
+        assert_eq!(resolved.include, ["*.ts"]);
+        assert!(resolve_options(None, None).unwrap().include.is_empty());
+
+        let err = resolve_options(Some(options("*.ts")), Some(profile.id)).unwrap_err();
+        assert!(err.to_string().contains("not both"));
+        assert!(resolve_options(None, Some("missing".to_string())).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter};
+
+use crate::git::CloneProgress;
+
+/// Minimum time between two progress events, so a parse of many small files
+/// does not flood the frontend. Phase changes are always sent.
+pub const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParsePhase {
+    /// Walking the inputs to find the files to parse.
+    Counting,
+    Writing,
+    /// Building `tree.json` from the walk.
+    Tree,
+    /// Writing the summary, metadata and report.
+    Finalizing,
+}
+
+/// Payload of the `parse-progress` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseProgress {
+    pub parse_id: String,
+    pub phase: ParsePhase,
+    /// Percentage done. Only reaches 100 once every output file is written.
+    pub parse_progress: f32,
+    pub files_amount: usize,
+    pub files_done: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_file: Option<String>,
+    pub elapsed_ms: u64,
+    /// Estimated time left while writing, from the rate bytes were written at.
+    pub eta_ms: Option<u64>,
+    /// Path of `content.txt`, set on the last event.
+    pub result_file_path: Option<String>,
+}
+
+/// Receives what a parse reports while it runs. The engine only talks to
+/// this trait, so it can run inside the app, from a CLI or from tests.
+pub trait ProgressSink: Send + Sync {
+    /// The parse got its id, which is what `ParseRegistry::cancel` takes.
+    fn started(&self, _parse_id: &str) {}
+
+    fn progress(&self, progress: &ParseProgress);
+
+    /// The parse was cancelled and its partial output removed.
+    fn cancelled(&self, _parse_id: &str) {}
+
+    /// Transfer progress of a repository being cloned for a parse.
+    fn clone_progress(&self, _progress: &CloneProgress) {}
+}
+
+/// Forwards everything as Tauri events: `parse-started`, `parse-progress`
+/// and `parse-cancelled`.
+pub struct TauriEventSink {
+    app: AppHandle,
+}
+
+impl TauriEventSink {
+    pub fn new(app: AppHandle) -> Self {
+        TauriEventSink { app }
+    }
+}
+
+impl ProgressSink for TauriEventSink {
+    fn started(&self, parse_id: &str) {
+        let _ = self
+            .app
+            .emit("parse-started", json!({ "parse_id": parse_id }));
+    }
+
+    fn progress(&self, progress: &ParseProgress) {
+        let _ = self.app.emit("parse-progress", progress);
+    }
+
+    fn cancelled(&self, parse_id: &str) {
+        let _ = self
+            .app
+            .emit("parse-cancelled", json!({ "parse_id": parse_id }));
+    }
+
+    fn clone_progress(&self, progress: &CloneProgress) {
+        let _ = self.app.emit("clone-progress", progress);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseEvent {
+    Started(String),
+    Progress(ParseProgress),
+    Cancelled(String),
+    Clone(CloneProgress),
+}
+
+/// Sends every report down a channel. Events are dropped once the receiver
+/// is gone.
+pub struct ChannelSink {
+    sender: mpsc::Sender<ParseEvent>,
+}
+
+impl ChannelSink {
+    pub fn new() -> (Self, mpsc::Receiver<ParseEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (ChannelSink { sender }, receiver)
+    }
+}
+
+impl ProgressSink for ChannelSink {
+    fn started(&self, parse_id: &str) {
+        let _ = self.sender.send(ParseEvent::Started(parse_id.to_string()));
+    }
+
+    fn progress(&self, progress: &ParseProgress) {
+        let _ = self.sender.send(ParseEvent::Progress(progress.clone()));
+    }
+
+    fn cancelled(&self, parse_id: &str) {
+        let _ = self
+            .sender
+            .send(ParseEvent::Cancelled(parse_id.to_string()));
+    }
+
+    fn clone_progress(&self, progress: &CloneProgress) {
+        let _ = self.sender.send(ParseEvent::Clone(progress.clone()));
+    }
+}
+
+/// Ignores all reports.
+pub struct NoopSink;
+
+impl ProgressSink for NoopSink {
+    fn progress(&self, _progress: &ParseProgress) {}
+}
+
+struct Counters {
+    phase: ParsePhase,
+    files_amount: usize,
+    files_done: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+    current_file: Option<String>,
+    writing_started: Option<Instant>,
+    last_emit: Option<Instant>,
+}
+
+/// Keeps the running totals of a parse and reports them to a sink as
+/// `ParseProgress`. Shared by the walk, the worker pool and the writer thread.
+pub struct ProgressTracker<'a> {
+    parse_id: String,
+    started: Instant,
+    sink: &'a dyn ProgressSink,
+    counters: Mutex<Counters>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(parse_id: &str, sink: &'a dyn ProgressSink) -> Self {
+        ProgressTracker {
+            parse_id: parse_id.to_string(),
+            started: Instant::now(),
+            sink,
+            counters: Mutex::new(Counters {
+                phase: ParsePhase::Counting,
+                files_amount: 0,
+                files_done: 0,
+                bytes_done: 0,
+                bytes_total: 0,
+                current_file: None,
+                writing_started: None,
+                last_emit: None,
+            }),
+        }
+    }
+
+    pub fn set_phase(&self, phase: ParsePhase) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.phase = phase;
+        if phase == ParsePhase::Writing {
+            counters.writing_started = Some(Instant::now());
+        }
+        self.send(&mut counters, None);
+    }
+
+    /// Adds files found while counting.
+    pub fn found(&self, files: usize, bytes: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.files_amount += files;
+        counters.bytes_total += bytes;
+        self.send_throttled(&mut counters);
+    }
+
+    /// Records a file as handled, whether it was written or skipped.
+    pub fn file_done(&self, path: &str, bytes: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.files_done += 1;
+        counters.bytes_done += bytes;
+        counters.current_file = Some(path.to_string());
+        self.send_throttled(&mut counters);
+    }
+
+    /// Sends the last event, at 100%.
+    pub fn finish(&self, result_file_path: &str) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.current_file = None;
+        self.send(&mut counters, Some(result_file_path.to_string()));
+    }
+
+    fn send_throttled(&self, counters: &mut Counters) {
+        let due = counters
+            .last_emit
+            .is_none_or(|last| last.elapsed() >= EMIT_INTERVAL);
+        if due {
+            self.send(counters, None);
+        }
+    }
+
+    fn send(&self, counters: &mut Counters, result_file_path: Option<String>) {
+        counters.last_emit = Some(Instant::now());
+
+        let fraction = if counters.bytes_total > 0 {
+            counters.bytes_done as f32 / counters.bytes_total as f32
+        } else if counters.files_amount > 0 {
+            counters.files_done as f32 / counters.files_amount as f32
+        } else {
+            0.0
+        };
+        let parse_progress = match (&result_file_path, counters.phase) {
+            (Some(_), _) => 100.0,
+            (None, ParsePhase::Counting) => 0.0,
+            (None, ParsePhase::Writing) => (fraction * 99.0).min(99.0),
+            (None, ParsePhase::Tree | ParsePhase::Finalizing) => 99.0,
+        };
+
+        let eta_ms = match (counters.phase, counters.writing_started) {
+            (ParsePhase::Writing, Some(started)) if counters.bytes_done > 0 => {
+                let elapsed = started.elapsed().as_secs_f64();
+                let remaining = counters.bytes_total.saturating_sub(counters.bytes_done);
+                Some((elapsed * remaining as f64 / counters.bytes_done as f64 * 1000.0) as u64)
+            }
+            _ => None,
+        };
+
+        self.sink.progress(&ParseProgress {
+            parse_id: self.parse_id.clone(),
+            phase: counters.phase,
+            parse_progress,
+            files_amount: counters.files_amount,
+            files_done: counters.files_done,
+            bytes_done: counters.bytes_done,
+            bytes_total: counters.bytes_total,
+            current_file: counters.current_file.clone(),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            eta_ms,
+            result_file_path,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn progress_events(receiver: &mpsc::Receiver<ParseEvent>) -> Vec<ParseProgress> {
+        receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                ParseEvent::Progress(progress) => Some(progress),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_within_the_interval_are_dropped_except_phase_changes() {
+        let (sink, receiver) = ChannelSink::new();
+        let tracker = ProgressTracker::new("parse", &sink);
+        tracker.found(1, 10);
+        tracker.found(1, 10);
+        tracker.found(1, 10);
+        tracker.set_phase(ParsePhase::Writing);
+        tracker.file_done("a", 10);
+
+        let events = progress_events(&receiver);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].files_amount, 1);
+        assert_eq!(events[1].phase, ParsePhase::Writing);
+        assert_eq!((events[1].files_amount, events[1].bytes_total), (3, 30));
+    }
+
+    #[test]
+    fn progress_follows_the_phases() {
+        let (sink, receiver) = ChannelSink::new();
+        let tracker = ProgressTracker::new("parse", &sink);
+        tracker.found(2, 100);
+        tracker.set_phase(ParsePhase::Writing);
+        thread::sleep(EMIT_INTERVAL);
+        tracker.file_done("a", 50);
+        tracker.set_phase(ParsePhase::Tree);
+        tracker.set_phase(ParsePhase::Finalizing);
+        tracker.finish("/out/content.txt");
+
+        let events = progress_events(&receiver);
+        let steps: Vec<(ParsePhase, f32)> =
+            events.iter().map(|e| (e.phase, e.parse_progress)).collect();
+        assert_eq!(
+            steps,
+            [
+                (ParsePhase::Counting, 0.0),
+                (ParsePhase::Writing, 0.0),
+                (ParsePhase::Writing, 49.5),
+                (ParsePhase::Tree, 99.0),
+                (ParsePhase::Finalizing, 99.0),
+                (ParsePhase::Finalizing, 100.0),
+            ]
+        );
+        assert_eq!(events[2].current_file.as_deref(), Some("a"));
+        let last = events.last().unwrap();
+        assert_eq!(last.result_file_path.as_deref(), Some("/out/content.txt"));
+        assert_eq!(last.current_file, None);
+    }
+
+    #[test]
+    fn eta_is_estimated_from_the_write_rate() {
+        let (sink, receiver) = ChannelSink::new();
+        let tracker = ProgressTracker::new("parse", &sink);
+        tracker.found(2, 100);
+        tracker.set_phase(ParsePhase::Writing);
+        thread::sleep(EMIT_INTERVAL * 2);
+        tracker.file_done("a", 50);
+        tracker.set_phase(ParsePhase::Tree);
+
+        let events = progress_events(&receiver);
+        assert_eq!(events[1].eta_ms, None);
+        // Half the bytes took about 200ms, so about as long again is left.
+        let eta = events[2].eta_ms.unwrap();
+        assert!((150..2000).contains(&eta), "{}", eta);
+        assert_eq!(events[3].eta_ms, None);
+    }
+
+    #[test]
+    fn channel_sinks_deliver_events_in_order() {
+        let (sink, receiver) = ChannelSink::new();
+        sink.started("parse");
+        ProgressTracker::new("parse", &sink).set_phase(ParsePhase::Writing);
+        sink.cancelled("parse");
+
+        let events: Vec<ParseEvent> = receiver.try_iter().collect();
+        assert!(matches!(&events[0], ParseEvent::Started(id) if id == "parse"));
+        assert!(matches!(
+            &events[1],
+            ParseEvent::Progress(progress)
+                if progress.parse_id == "parse" && progress.phase == ParsePhase::Writing
+        ));
+        assert!(matches!(&events[2], ParseEvent::Cancelled(id) if id == "parse"));
+        assert_eq!(events.len(), 3);
+
+        drop(receiver);
+        sink.cancelled("parse");
+    }
+}
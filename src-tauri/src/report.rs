@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    Binary,
+    /// Only found in reports of older versions, which skipped text that was
+    /// not UTF-8 instead of transcoding it.
+    NonUtf8,
+    /// Text that is invalid in the encoding it was detected to be in, or in
+    /// an encoding that is not supported.
+    Undecodable,
+    PermissionDenied,
+    Symlink,
+    Hidden,
+    /// Matched by a `.gitignore`, `.ignore` or git exclude file.
+    Ignored,
+    /// Rejected by the parse's include/exclude patterns.
+    Excluded,
+    Oversized,
+    Error,
+}
+
+impl SkipReason {
+    pub fn from_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => SkipReason::PermissionDenied,
+            io::ErrorKind::InvalidData => SkipReason::Undecodable,
+            _ => SkipReason::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedPath {
+    pub path: String,
+    pub reason: SkipReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Contents of `report.json`: everything a parse left out and why.
+/// Ignored and excluded directories are listed once, not per file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParseReport {
+    pub skipped: Vec<SkippedPath>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_map_to_skip_reasons() {
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            SkipReason::from_io_error(&error),
+            SkipReason::PermissionDenied
+        );
+        let error = io::Error::from(io::ErrorKind::InvalidData);
+        assert_eq!(SkipReason::from_io_error(&error), SkipReason::Undecodable);
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(SkipReason::from_io_error(&error), SkipReason::Error);
+    }
+
+    #[test]
+    fn reports_serialize_reasons_in_snake_case() {
+        let report = ParseReport {
+            skipped: vec![
+                SkippedPath {
+                    path: "data.txt".to_string(),
+                    reason: SkipReason::Undecodable,
+                    detail: None,
+                },
+                SkippedPath {
+                    path: "secret".to_string(),
+                    reason: SkipReason::PermissionDenied,
+                    detail: Some("Permission denied".to_string()),
+                },
+            ],
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "skipped": [
+                    { "path": "data.txt", "reason": "undecodable" },
+                    {
+                        "path": "secret",
+                        "reason": "permission_denied",
+                        "detail": "Permission denied"
+                    }
+                ]
+            })
+        );
+    }
+}
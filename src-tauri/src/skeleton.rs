@@ -0,0 +1,871 @@
+use tree_sitter::{Language, Node, Parser};
+
+// A skeleton keeps the public surface of a file: function and method
+// signatures with their bodies elided, and type definitions (structs, enums,
+// traits, interfaces, aliases) in full. Doc comments and docstrings stay
+// with the items they document. Items are emitted with their original
+// indentation so nested members still read naturally.
+
+#[derive(Debug, Clone, Copy)]
+enum Grammar {
+    Rust,
+    TypeScript,
+    Tsx,
+    Python,
+    Go,
+}
+
+impl Grammar {
+    fn for_language(language: &str) -> Option<Self> {
+        Some(match language {
+            "rust" => Grammar::Rust,
+            "typescript" => Grammar::TypeScript,
+            "tsx" => Grammar::Tsx,
+            "python" => Grammar::Python,
+            "go" => Grammar::Go,
+            _ => return None,
+        })
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Grammar::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Grammar::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Grammar::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Grammar::Python => tree_sitter_python::LANGUAGE.into(),
+            Grammar::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+}
+
+/// Signature-only version of `source`, or `None` when there is no grammar
+/// for the language or `source` does not parse cleanly, since a skeleton of
+/// a misread file could drop arbitrary parts of it.
+pub fn extract_skeleton(language: &str, source: &str) -> Option<String> {
+    let grammar = Grammar::for_language(language)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language()).ok()?;
+    let tree = parser.parse(source, None)?;
+    let root = tree.root_node();
+    if root.has_error() {
+        return None;
+    }
+
+    let mut skeleton = Skeleton {
+        grammar,
+        src: source,
+        out: String::new(),
+    };
+    match grammar {
+        Grammar::Rust => skeleton.rust_items(root, 0, false),
+        Grammar::TypeScript | Grammar::Tsx => skeleton.ts_items(root, 0),
+        Grammar::Python => skeleton.python_items(root, 0, false),
+        Grammar::Go => skeleton.go_items(root),
+    }
+    Some(skeleton.out)
+}
+
+struct Skeleton<'a> {
+    grammar: Grammar,
+    src: &'a str,
+    out: String,
+}
+
+impl<'a> Skeleton<'a> {
+    fn text(&self, node: Node) -> &'a str {
+        &self.src[node.byte_range()]
+    }
+
+    fn line_start(&self, byte: usize) -> usize {
+        self.src[..byte].rfind('\n').map_or(0, |p| p + 1)
+    }
+
+    fn indent_of(&self, byte: usize) -> &'a str {
+        let start = self.line_start(byte);
+        let line = &self.src[start..byte];
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    /// Start of the doc comments directly above `first`, the first node of an
+    /// item, or of `first` itself when there are none.
+    fn doc_start(&self, first: Node) -> usize {
+        let mut start = first.start_byte();
+        let mut next = first;
+        while let Some(prev) = next.prev_sibling() {
+            let adjacent = self.src[prev.end_byte()..next.start_byte()]
+                .matches('\n')
+                .count()
+                <= 1;
+            if !adjacent || !self.is_doc_comment(prev) {
+                break;
+            }
+            start = prev.start_byte();
+            next = prev;
+        }
+        start
+    }
+
+    fn is_doc_comment(&self, node: Node) -> bool {
+        let text = self.text(node);
+        match self.grammar {
+            Grammar::Rust => {
+                matches!(node.kind(), "line_comment" | "block_comment")
+                    && ((text.starts_with("///") && !text.starts_with("////"))
+                        || (text.starts_with("/**") && !text.starts_with("/***") && text != "/**/"))
+            }
+            Grammar::TypeScript | Grammar::Tsx => {
+                node.kind() == "comment" && text.starts_with("/**") && text != "/**/"
+            }
+            // Go documents declarations with the plain comments above them.
+            Grammar::Go => node.kind() == "comment",
+            // Python has docstrings instead, see `python_docstring`.
+            Grammar::Python => false,
+        }
+    }
+
+    /// Top-level items are separated by a blank line.
+    fn begin_item(&mut self, depth: usize) {
+        if depth == 0 && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    /// Emits an item as written, starting at `start` (which may point at an
+    /// attribute or `export` keyword before the node itself).
+    fn keep(&mut self, start: usize, node: Node, depth: usize) {
+        self.begin_item(depth);
+        let from = self.line_start(start);
+        self.out
+            .push_str(self.src[from..node.end_byte()].trim_end());
+        self.out.push('\n');
+    }
+
+    /// Emits everything before `body`, followed by `elided` in its place.
+    fn elide(&mut self, start: usize, body: Node, elided: &str, depth: usize) {
+        self.begin_item(depth);
+        let from = self.line_start(start);
+        self.out
+            .push_str(self.src[from..body.start_byte()].trim_end());
+        self.out.push_str(elided);
+        self.out.push('\n');
+    }
+
+    /// Emits a braced item whose members are filtered by `members`. Unless
+    /// `always` is set, the item is dropped when none of its members are.
+    fn container(
+        &mut self,
+        start: usize,
+        node: Node,
+        body: Node,
+        depth: usize,
+        always: bool,
+        members: impl FnOnce(&mut Self, Node),
+    ) {
+        let mark = self.out.len();
+        self.begin_item(depth);
+        let from = self.line_start(start);
+        self.out
+            .push_str(self.src[from..body.start_byte()].trim_end());
+        self.out.push_str(" {\n");
+
+        let body_mark = self.out.len();
+        members(self, body);
+        if self.out.len() == body_mark && !always {
+            self.out.truncate(mark);
+            return;
+        }
+
+        let indent = self.indent_of(node.start_byte());
+        self.out.push_str(indent);
+        self.out.push_str("}\n");
+    }
+
+    // /////////////////////////////////////////////////////////////////////////
+    // Rust
+    // /////////////////////////////////////////////////////////////////////////
+
+    /// `all_public` is set inside traits and trait impls, whose members
+    /// carry no visibility of their own.
+    fn rust_items(&mut self, list: Node, depth: usize, all_public: bool) {
+        let mut cursor = list.walk();
+        let mut first_attribute = None;
+
+        for child in list.named_children(&mut cursor) {
+            match child.kind() {
+                "attribute_item" => {
+                    first_attribute.get_or_insert(child);
+                    continue;
+                }
+                // Doc comments are picked up by `doc_start` along with the
+                // item they precede.
+                "line_comment" | "block_comment" => continue,
+                _ => {}
+            }
+            let start = self.doc_start(first_attribute.take().unwrap_or(child));
+            let public = all_public || has_child(child, "visibility_modifier");
+
+            match child.kind() {
+                "function_item" if public => {
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.elide(start, body, " { ... }", depth);
+                    }
+                }
+                "function_signature_item"
+                | "associated_type"
+                | "struct_item"
+                | "enum_item"
+                | "union_item"
+                | "type_item"
+                | "const_item"
+                | "static_item"
+                    if public =>
+                {
+                    self.keep(start, child, depth)
+                }
+                "trait_item" if public => {
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.container(start, child, body, depth, true, |s, body| {
+                            s.rust_items(body, depth + 1, true)
+                        });
+                    }
+                }
+                "impl_item" => {
+                    let trait_impl = child.child_by_field_name("trait").is_some();
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.container(start, child, body, depth, trait_impl, |s, body| {
+                            s.rust_items(body, depth + 1, trait_impl)
+                        });
+                    }
+                }
+                "mod_item" if public => match child.child_by_field_name("body") {
+                    Some(body) => self.container(start, child, body, depth, false, |s, body| {
+                        s.rust_items(body, depth + 1, false)
+                    }),
+                    None => self.keep(start, child, depth),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    // /////////////////////////////////////////////////////////////////////////
+    // TypeScript
+    // /////////////////////////////////////////////////////////////////////////
+
+    fn ts_items(&mut self, list: Node, depth: usize) {
+        let mut cursor = list.walk();
+        for child in list.named_children(&mut cursor) {
+            match child.kind() {
+                "export_statement" => match child.child_by_field_name("declaration") {
+                    Some(declaration) => {
+                        self.ts_declaration(self.doc_start(child), declaration, depth)
+                    }
+                    // Re-exports and `export default <expression>`.
+                    None => self.keep(self.doc_start(child), child, depth),
+                },
+                "ambient_declaration" => self.keep(self.doc_start(child), child, depth),
+                _ => {}
+            }
+        }
+    }
+
+    fn ts_declaration(&mut self, start: usize, declaration: Node, depth: usize) {
+        match declaration.kind() {
+            "function_declaration" | "generator_function_declaration" => {
+                if let Some(body) = declaration.child_by_field_name("body") {
+                    self.elide(start, body, " { ... }", depth);
+                }
+            }
+            "class_declaration" | "abstract_class_declaration" => {
+                if let Some(body) = declaration.child_by_field_name("body") {
+                    self.container(start, declaration, body, depth, true, |s, body| {
+                        s.ts_class_members(body, depth + 1)
+                    });
+                }
+            }
+            "internal_module" | "module" => match declaration.child_by_field_name("body") {
+                Some(body) => self.container(start, declaration, body, depth, false, |s, body| {
+                    s.ts_items(body, depth + 1)
+                }),
+                None => self.keep(start, declaration, depth),
+            },
+            "lexical_declaration" | "variable_declaration" => {
+                self.ts_variables(start, declaration, depth)
+            }
+            _ => self.keep(start, declaration, depth),
+        }
+    }
+
+    fn ts_variables(&mut self, start: usize, declaration: Node, depth: usize) {
+        let mut cursor = declaration.walk();
+        let declarators: Vec<Node> = declaration
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "variable_declarator")
+            .collect();
+        let value = match declarators.as_slice() {
+            [declarator] => declarator.child_by_field_name("value"),
+            _ => None,
+        };
+
+        match value {
+            Some(value) if matches!(value.kind(), "arrow_function" | "function_expression") => {
+                match value.child_by_field_name("body") {
+                    Some(body) if body.kind() == "statement_block" => {
+                        self.elide(start, body, " { ... }", depth)
+                    }
+                    _ => self.keep(start, declaration, depth),
+                }
+            }
+            Some(value) if self.text(declaration).contains('\n') => {
+                self.elide(start, value, " ...", depth)
+            }
+            _ => self.keep(start, declaration, depth),
+        }
+    }
+
+    fn ts_class_members(&mut self, body: Node, depth: usize) {
+        let mut cursor = body.walk();
+        for member in body.named_children(&mut cursor) {
+            let private = member
+                .child_by_field_name("name")
+                .is_some_and(|name| name.kind() == "private_property_identifier")
+                || {
+                    let mut cursor = member.walk();
+                    let private = member
+                        .children(&mut cursor)
+                        .any(|c| c.kind() == "accessibility_modifier" && self.text(c) == "private");
+                    private
+                };
+            if private {
+                continue;
+            }
+            let start = self.doc_start(member);
+
+            match member.kind() {
+                "method_definition" => {
+                    if let Some(body) = member.child_by_field_name("body") {
+                        self.elide(start, body, " { ... }", depth);
+                    }
+                }
+                "public_field_definition"
+                | "method_signature"
+                | "abstract_method_signature"
+                | "index_signature" => {
+                    // The terminating `;` is a sibling of the member.
+                    let end = member
+                        .next_sibling()
+                        .filter(|next| next.kind() == ";")
+                        .unwrap_or(member);
+                    self.keep(start, end, depth)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // /////////////////////////////////////////////////////////////////////////
+    // Python
+    // /////////////////////////////////////////////////////////////////////////
+
+    fn python_items(&mut self, block: Node, depth: usize, in_class: bool) {
+        let mut cursor = block.walk();
+        for child in block.named_children(&mut cursor) {
+            let start = child.start_byte();
+            let definition = match child.kind() {
+                "decorated_definition" => match child.child_by_field_name("definition") {
+                    Some(definition) => definition,
+                    None => continue,
+                },
+                _ => child,
+            };
+            let public = definition
+                .child_by_field_name("name")
+                .is_some_and(|name| is_public_python_name(self.text(name)));
+
+            match definition.kind() {
+                "function_definition" if public => {
+                    if let Some(body) = definition.child_by_field_name("body") {
+                        self.python_function(start, definition, body, depth);
+                    }
+                }
+                "class_definition" if public => {
+                    if let Some(body) = definition.child_by_field_name("body") {
+                        self.python_class(start, definition, body, depth);
+                    }
+                }
+                // Annotated class attributes, e.g. dataclass fields.
+                "expression_statement" if in_class => {
+                    let mut cursor = child.walk();
+                    let field = child.named_children(&mut cursor).any(|c| {
+                        c.kind() == "assignment"
+                            && c.child_by_field_name("type").is_some()
+                            && c.child_by_field_name("left")
+                                .is_some_and(|left| is_public_python_name(self.text(left)))
+                    });
+                    if field {
+                        self.keep(start, child, depth);
+                    }
+                }
+                "type_alias_statement" => self.keep(start, child, depth),
+                _ => {}
+            }
+        }
+    }
+
+    /// Elides the body of a function, keeping its docstring.
+    fn python_function(&mut self, start: usize, function: Node, body: Node, depth: usize) {
+        let indent = self.python_body_indent(function, body);
+        match self.python_docstring(function, body) {
+            Some(docstring) => {
+                self.keep(start, docstring, depth);
+                self.out.push_str(&format!("{}...\n", indent));
+            }
+            None => self.elide(start, body, &format!("\n{}...", indent), depth),
+        }
+    }
+
+    fn python_class(&mut self, start: usize, class: Node, body: Node, depth: usize) {
+        self.begin_item(depth);
+        let from = self.line_start(start);
+        let docstring = self.python_docstring(class, body);
+        let header_end = docstring.map_or(body.start_byte(), |docstring| docstring.end_byte());
+        self.out.push_str(self.src[from..header_end].trim_end());
+        self.out.push('\n');
+        if docstring.is_some() {
+            // The docstring alone is a valid body.
+            self.python_items(body, depth + 1, true);
+            return;
+        }
+
+        let body_mark = self.out.len();
+        self.python_items(body, depth + 1, true);
+        if self.out.len() == body_mark {
+            let indent = self.python_body_indent(class, body);
+            self.out.push_str(&format!("{}...\n", indent));
+        }
+    }
+
+    /// The string a definition's body starts with, when the body is on its
+    /// own lines.
+    fn python_docstring<'t>(&self, definition: Node, body: Node<'t>) -> Option<Node<'t>> {
+        if body.start_position().row == definition.start_position().row {
+            return None;
+        }
+        let statement = body.named_child(0)?;
+        let string = statement.named_child(0)?;
+        (statement.kind() == "expression_statement"
+            && statement.named_child_count() == 1
+            && string.kind() == "string")
+            .then_some(statement)
+    }
+
+    fn python_body_indent(&self, definition: Node, body: Node) -> String {
+        if body.start_position().row == definition.start_position().row {
+            format!("{}    ", self.indent_of(definition.start_byte()))
+        } else {
+            self.indent_of(body.start_byte()).to_string()
+        }
+    }
+
+    // /////////////////////////////////////////////////////////////////////////
+    // Go
+    // /////////////////////////////////////////////////////////////////////////
+
+    fn go_items(&mut self, root: Node) {
+        let mut cursor = root.walk();
+        for child in root.named_children(&mut cursor) {
+            let exported = child
+                .child_by_field_name("name")
+                .is_some_and(|name| is_exported_go_name(self.text(name)));
+
+            let start = self.doc_start(child);
+
+            match child.kind() {
+                "package_clause" => self.keep(start, child, 0),
+                "function_declaration" | "method_declaration" if exported => {
+                    if let Some(body) = child.child_by_field_name("body") {
+                        self.elide(start, body, " { ... }", 0);
+                    }
+                }
+                "type_declaration" => self.go_types(start, child),
+                _ => {}
+            }
+        }
+    }
+
+    fn go_types(&mut self, start: usize, declaration: Node) {
+        let mut cursor = declaration.walk();
+        let specs: Vec<Node> = declaration
+            .named_children(&mut cursor)
+            .filter(|c| matches!(c.kind(), "type_spec" | "type_alias"))
+            .collect();
+        let exported: Vec<Node> = specs
+            .iter()
+            .copied()
+            .filter(|spec| {
+                spec.child_by_field_name("name")
+                    .is_some_and(|name| is_exported_go_name(self.text(name)))
+            })
+            .collect();
+
+        if specs.len() == 1 && exported.len() == 1 {
+            self.keep(start, declaration, 0);
+            return;
+        }
+        // Grouped declarations are split up so unexported types can be left
+        // out.
+        for spec in exported {
+            self.begin_item(0);
+            self.out.push_str("type ");
+            self.out.push_str(self.text(spec));
+            self.out.push('\n');
+        }
+    }
+}
+
+fn has_child(node: Node, kind: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|c| c.kind() == kind);
+    found
+}
+
+fn is_public_python_name(name: &str) -> bool {
+    !name.starts_with('_') || (name.starts_with("__") && name.ends_with("__"))
+}
+
+fn is_exported_go_name(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_uppercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skeleton(language: &str, source: &str) -> String {
+        extract_skeleton(language, source).unwrap()
+    }
+
+    #[test]
+    fn rust_keeps_public_signatures_and_types() {
+        let source = r#"use std::fmt;
+
+#[derive(Debug)]
+pub struct Point {
+    pub x: i32,
+}
+
+fn helper() -> i32 {
+    1
+}
+
+impl Point {
+    #[inline]
+    pub fn new(x: i32) -> Self {
+        Point { x }
+    }
+
+    fn secret(&self) {}
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.x)
+    }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+"#;
+        assert_eq!(
+            skeleton("rust", source),
+            r#"#[derive(Debug)]
+pub struct Point {
+    pub x: i32,
+}
+
+impl Point {
+    #[inline]
+    pub fn new(x: i32) -> Self { ... }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { ... }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn typescript_keeps_exports_and_public_members() {
+        let source = r#"import { x } from "./x";
+
+export interface Options {
+  verbose: boolean;
+}
+
+export class Runner {
+  private count = 0;
+  name: string;
+
+  run(options: Options): void {
+    this.count++;
+  }
+}
+
+export const double = (n: number): number => {
+  return n * 2;
+};
+
+function internal() {}
+"#;
+        assert_eq!(
+            skeleton("typescript", source),
+            r#"export interface Options {
+  verbose: boolean;
+}
+
+export class Runner {
+  name: string;
+  run(options: Options): void { ... }
+}
+
+export const double = (n: number): number => { ... }
+"#
+        );
+    }
+
+    #[test]
+    fn tsx_components_are_elided() {
+        let source = "export function App(props: Props) {\n  return <div>{props.title}</div>;\n}\n";
+        assert_eq!(
+            skeleton("tsx", source),
+            "export function App(props: Props) { ... }\n"
+        );
+    }
+
+    #[test]
+    fn python_keeps_decorated_public_definitions() {
+        let source = r#"import os
+
+@dataclass
+class Config:
+    path: str
+    _cache: dict
+
+    def load(self) -> dict:
+        return {}
+
+    def _read(self):
+        pass
+
+def _private():
+    pass
+"#;
+        assert_eq!(
+            skeleton("python", source),
+            r#"@dataclass
+class Config:
+    path: str
+    def load(self) -> dict:
+        ...
+"#
+        );
+    }
+
+    #[test]
+    fn go_keeps_exported_declarations() {
+        let source = r#"package server
+
+import "net/http"
+
+type (
+	Server struct {
+		Addr string
+	}
+	handler func()
+)
+
+func (s *Server) Start() error {
+	return http.ListenAndServe(s.Addr, nil)
+}
+
+func helper() {}
+"#;
+        assert_eq!(
+            skeleton("go", source),
+            r#"package server
+
+type Server struct {
+		Addr string
+	}
+
+func (s *Server) Start() error { ... }
+"#
+        );
+    }
+
+    #[test]
+    fn unsupported_languages_have_no_skeleton() {
+        assert_eq!(extract_skeleton("ruby", "def a; end"), None);
+        assert_eq!(extract_skeleton("markdown", "# Title"), None);
+    }
+
+    #[test]
+    fn rust_doc_comments_stay_with_their_items() {
+        let source = r#"//! Crate docs.
+
+/// A point.
+///
+/// With two lines of docs.
+#[derive(Debug)]
+pub struct Point;
+
+// Not documentation.
+pub fn plain() {}
+
+/** Block docs. */
+pub fn block() -> u8 {
+    0
+}
+
+/// Docs of a private item.
+fn hidden() {}
+
+pub trait Shape {
+    /// Area of the shape.
+    fn area(&self) -> f64;
+}
+"#;
+        assert_eq!(
+            skeleton("rust", source),
+            r#"/// A point.
+///
+/// With two lines of docs.
+#[derive(Debug)]
+pub struct Point;
+
+pub fn plain() { ... }
+
+/** Block docs. */
+pub fn block() -> u8 { ... }
+
+pub trait Shape {
+    /// Area of the shape.
+    fn area(&self) -> f64;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn typescript_doc_comments_stay_with_their_items() {
+        let source = r#"/** Runs things. */
+export class Runner {
+  /** Runs once. */
+  run(): void {
+    go();
+  }
+}
+
+// A plain comment.
+export function plain() {}
+"#;
+        assert_eq!(
+            skeleton("typescript", source),
+            r#"/** Runs things. */
+export class Runner {
+  /** Runs once. */
+  run(): void { ... }
+}
+
+export function plain() { ... }
+"#
+        );
+    }
+
+    #[test]
+    fn python_docstrings_are_kept() {
+        let source = r#"class Config:
+    """Settings read from disk."""
+
+    def load(self, path: str) -> dict:
+        """Reads `path`.
+
+        Returns the parsed settings.
+        """
+        with open(path) as f:
+            return parse(f)
+
+class Empty:
+    """Nothing in here."""
+
+def run(): return 1
+"#;
+        assert_eq!(
+            skeleton("python", source),
+            r#"class Config:
+    """Settings read from disk."""
+    def load(self, path: str) -> dict:
+        """Reads `path`.
+
+        Returns the parsed settings.
+        """
+        ...
+
+class Empty:
+    """Nothing in here."""
+
+def run():
+    ...
+"#
+        );
+    }
+
+    #[test]
+    fn go_doc_comments_stay_with_their_declarations() {
+        let source = r#"// Package server serves things.
+package server
+
+// Server listens on Addr.
+type Server struct {
+	Addr string
+}
+
+// Start starts the server.
+// It blocks until it stops.
+func (s *Server) Start() error {
+	return nil
+}
+
+// helper is not exported.
+func helper() {}
+"#;
+        assert_eq!(
+            skeleton("go", source),
+            r#"// Package server serves things.
+package server
+
+// Server listens on Addr.
+type Server struct {
+	Addr string
+}
+
+// Start starts the server.
+// It blocks until it stops.
+func (s *Server) Start() error { ... }
+"#
+        );
+    }
+
+    #[test]
+    fn sources_that_do_not_parse_have_no_skeleton() {
+        assert_eq!(extract_skeleton("rust", "pub fn broken( {\n"), None);
+        assert_eq!(extract_skeleton("python", "def broken(:\n    pass\n"), None);
+        assert_eq!(extract_skeleton("go", "package a\nfunc {"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, ops::Range};
+
+/// A file as it was written to the output, in output order.
+#[derive(Debug, Clone)]
+pub struct WrittenFile {
+    pub path: String,
+    pub size: u64,
+    pub tokens: u64,
+    pub language: Option<&'static str>,
+    /// Where the content itself lies in `content.txt`.
+    pub content_range: Range<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceInfo {
+    pub remote_url: Option<String>,
+    pub commit: Option<String>,
+    pub git_ref: Option<String>,
+}
+
+pub fn render_summary(files: &[WrittenFile], source: &SourceInfo) -> String {
+    let total_size: u64 = files.iter().map(|f| f.size).sum();
+    let total_tokens: u64 = files.iter().map(|f| f.tokens).sum();
+
+    let mut languages: BTreeMap<&str, usize> = BTreeMap::new();
+    for file in files {
+        *languages
+            .entry(file.language.unwrap_or("other"))
+            .or_default() += 1;
+    }
+    let mut languages: Vec<(&str, usize)> = languages.into_iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let languages = languages
+        .iter()
+        .map(|(name, count)| format!("{} ({})", name, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut summary = String::new();
+    summary.push_str("Summary\n");
+    summary.push_str(&format!("Files: {}\n", files.len()));
+    summary.push_str(&format!(
+        "Total size: {} ({} bytes)\n",
+        format_size(total_size),
+        total_size
+    ));
+    summary.push_str(&format!("Tokens: {}\n", total_tokens));
+    if !languages.is_empty() {
+        summary.push_str(&format!("Languages: {}\n", languages));
+    }
+    if let Some(remote_url) = &source.remote_url {
+        summary.push_str(&format!("Source: {}\n", remote_url));
+    }
+    if let Some(git_ref) = &source.git_ref {
+        summary.push_str(&format!("Ref: {}\n", git_ref));
+    }
+    if let Some(commit) = &source.commit {
+        summary.push_str(&format!("Commit: {}\n", commit));
+    }
+
+    summary.push_str("\nDirectory structure:\n");
+    summary.push_str(&render_tree(files.iter().map(|f| f.path.as_str())));
+    summary
+}
+
+#[derive(Default)]
+struct TreeNode<'a> {
+    children: Vec<(&'a str, TreeNode<'a>)>,
+}
+
+impl<'a> TreeNode<'a> {
+    fn insert(&mut self, mut components: std::str::Split<'a, char>) {
+        let Some(name) = components.next() else {
+            return;
+        };
+        let index = match self.children.iter().position(|(n, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                self.children.push((name, TreeNode::default()));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].1.insert(components);
+    }
+
+    fn render(&self, prefix: &str, out: &mut String) {
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let is_last = i + 1 == self.children.len();
+            out.push_str(prefix);
+            out.push_str(if is_last { "└── " } else { "├── " });
+            out.push_str(name);
+            out.push('\n');
+
+            let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+            child.render(&child_prefix, out);
+        }
+    }
+}
+
+/// Renders `/`-separated paths like the `tree` command, keeping their order.
+pub fn render_tree<'a>(paths: impl Iterator<Item = &'a str>) -> String {
+    let mut root = TreeNode::default();
+    for path in paths {
+        root.insert(path.split('/'));
+    }
+
+    let mut out = String::from(".\n");
+    root.render("", &mut out);
+    out
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64, language: Option<&'static str>) -> WrittenFile {
+        WrittenFile {
+            path: path.to_string(),
+            size,
+            tokens: size / 4,
+            language,
+            content_range: 0..0,
+        }
+    }
+
+    #[test]
+    fn trees_keep_the_output_order() {
+        let tree = render_tree(["src/main.rs", "src/walk/mod.rs", "README.md"].into_iter());
+        assert_eq!(
+            tree,
+            ".\n├── src\n│   ├── main.rs\n│   └── walk\n│       └── mod.rs\n└── README.md\n"
+        );
+    }
+
+    #[test]
+    fn summaries_count_files_sizes_and_languages() {
+        let files = [
+            file("a.rs", 1024, Some("rust")),
+            file("b.rs", 1024, Some("rust")),
+            file("c.py", 512, Some("python")),
+            file("LICENSE", 0, None),
+        ];
+        let source = SourceInfo {
+            remote_url: Some("https://github.com/o/r".to_string()),
+            commit: Some("abc123".to_string()),
+            git_ref: Some("main".to_string()),
+        };
+
+        let summary = render_summary(&files, &source);
+        assert!(summary.starts_with(
+            "Summary\nFiles: 4\nTotal size: 2.5 KB (2560 bytes)\nTokens: 640\n\
+             Languages: rust (2), other (1), python (1)\n\
+             Source: https://github.com/o/r\nRef: main\nCommit: abc123\n\nDirectory structure:\n.\n"
+        ));
+    }
+
+    #[test]
+    fn sizes_use_binary_units() {
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
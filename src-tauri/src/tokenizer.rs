@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    /// GPT-4 / GPT-3.5 vocabulary.
+    #[default]
+    Cl100k,
+    /// GPT-4o vocabulary.
+    O200k,
+    /// Fast estimate that never runs the BPE merges.
+    Approximate,
+}
+
+pub fn count_tokens(kind: TokenizerKind, text: &str) -> u64 {
+    match kind {
+        TokenizerKind::Cl100k => tiktoken_rs::cl100k_base_singleton()
+            .encode_ordinary(text)
+            .len() as u64,
+        TokenizerKind::O200k => tiktoken_rs::o200k_base_singleton()
+            .encode_ordinary(text)
+            .len() as u64,
+        TokenizerKind::Approximate => approximate_tokens(text),
+    }
+}
+
+// BPE vocabularies keep most identifiers and English words whole or split
+// them into a couple of pieces, runs of punctuation merge in pairs and a
+// line break together with the indentation after it is a single token. A
+// single space merges into the word after it, longer runs of spaces are a
+// token of their own. Non-ASCII characters are counted as roughly one token
+// each.
+fn approximate_tokens(text: &str) -> u64 {
+    let mut tokens = 0u64;
+    let mut word_len = 0u64;
+    let mut punct_len = 0u64;
+    let mut space_len = 0u64;
+    let mut newlines = 0u64;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            tokens += word_len.div_ceil(6) + punct_len.div_ceil(2);
+            word_len = 0;
+            punct_len = 0;
+            if c == '\n' {
+                newlines += 1;
+            } else {
+                space_len += 1;
+            }
+            continue;
+        }
+        tokens += whitespace_tokens(space_len, newlines);
+        space_len = 0;
+        newlines = 0;
+
+        if c.is_ascii_alphanumeric() || c == '_' {
+            tokens += punct_len.div_ceil(2);
+            punct_len = 0;
+            word_len += 1;
+        } else if c.is_ascii() {
+            tokens += word_len.div_ceil(6);
+            word_len = 0;
+            punct_len += 1;
+        } else {
+            tokens += word_len.div_ceil(6) + punct_len.div_ceil(2) + 1;
+            word_len = 0;
+            punct_len = 0;
+        }
+    }
+
+    tokens + word_len.div_ceil(6) + punct_len.div_ceil(2) + whitespace_tokens(space_len, newlines)
+}
+
+fn whitespace_tokens(space_len: u64, newlines: u64) -> u64 {
+    match (newlines, space_len) {
+        (0, 0 | 1) => 0,
+        (0, _) => 1,
+        (newlines, _) => newlines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bpe_tokenizers_count_exactly() {
+        assert_eq!(count_tokens(TokenizerKind::Cl100k, ""), 0);
+        assert_eq!(count_tokens(TokenizerKind::Cl100k, "hello world"), 2);
+        assert_eq!(count_tokens(TokenizerKind::O200k, "hello world"), 2);
+    }
+
+    #[test]
+    fn approximation_stays_close_to_cl100k() {
+        let source = include_str!("walk.rs");
+        let exact = count_tokens(TokenizerKind::Cl100k, source) as f64;
+        let approximate = count_tokens(TokenizerKind::Approximate, source) as f64;
+        assert!(
+            (approximate / exact - 1.0).abs() < 0.25,
+            "{} vs {}",
+            approximate,
+            exact
+        );
+    }
+
+    #[test]
+    fn approximation_counts_words_punctuation_and_lines() {
+        assert_eq!(approximate_tokens(""), 0);
+        assert_eq!(approximate_tokens("fn main"), 2);
+        assert_eq!(approximate_tokens("a_very_long_identifier"), 4);
+        assert_eq!(approximate_tokens("a\n\nb"), 4);
+        assert_eq!(approximate_tokens("(){}"), 2);
+    }
+
+    #[test]
+    fn single_spaces_merge_into_the_next_word() {
+        assert_eq!(approximate_tokens("a b c"), 3);
+        assert_eq!(approximate_tokens("identifier"), 2);
+    }
+
+    #[test]
+    fn whitespace_runs_count_as_one_token() {
+        assert_eq!(approximate_tokens("        "), 1);
+        assert_eq!(approximate_tokens("a        b"), 3);
+        assert_eq!(approximate_tokens("a\t\tb"), 3);
+        assert_eq!(approximate_tokens("a\n    b"), 3);
+    }
+}
@@ -0,0 +1,952 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::language;
+use crate::skeleton;
+use crate::utils::ParseOptions;
+use crate::walk::PatternSet;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentMode {
+    #[default]
+    Full,
+    /// Public signatures only, for languages with a skeleton extractor.
+    Skeleton,
+}
+
+/// Selects a content mode for the files matching `pattern`, e.g.
+/// `{ "pattern": "src/core", "mode": "full" }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentModeRule {
+    pub pattern: String,
+    pub mode: ContentMode,
+}
+
+/// Changes applied to a file's content after it is read and before it is
+/// written to the output.
+#[derive(Clone, Default)]
+pub struct Transform {
+    root: PathBuf,
+    mode: ContentMode,
+    mode_rules: Vec<(Arc<PatternSet>, ContentMode)>,
+    strip_comments: bool,
+    collapse_blank_lines: bool,
+}
+
+impl Transform {
+    pub fn new(options: &ParseOptions) -> Result<Self> {
+        let mut mode_rules = Vec::new();
+        for rule in &options.content_mode_rules {
+            if let Some(patterns) = PatternSet::build(&[rule.pattern.as_str()])? {
+                mode_rules.push((Arc::new(patterns), rule.mode));
+            }
+        }
+
+        Ok(Transform {
+            root: PathBuf::new(),
+            mode: options.content_mode,
+            mode_rules,
+            strip_comments: options.strip_comments,
+            collapse_blank_lines: options.collapse_blank_lines,
+        })
+    }
+
+    pub fn with_root(&self, root: &Path) -> Self {
+        Transform {
+            root: root.to_path_buf(),
+            ..self.clone()
+        }
+    }
+
+    /// The mode of the last rule matching `path`, or the parse-wide mode.
+    pub fn mode_for(&self, path: &Path) -> ContentMode {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.mode_rules
+            .iter()
+            .rev()
+            .find(|(patterns, _)| patterns.is_match_within(relative))
+            .map_or(self.mode, |(_, mode)| *mode)
+    }
+
+    pub fn apply<'a>(&self, source: &Path, content: &'a str) -> Cow<'a, str> {
+        let language = language::detect_language(source);
+        let mut content = Cow::Borrowed(content);
+
+        if self.mode_for(source) == ContentMode::Skeleton {
+            if let Some(skeleton) =
+                language.and_then(|language| skeleton::extract_skeleton(language, &content))
+            {
+                content = Cow::Owned(skeleton);
+            }
+        }
+        if self.strip_comments {
+            if let Some(dialect) = language.and_then(dialect_for) {
+                content = Cow::Owned(strip_comments(&content, dialect));
+            }
+        }
+        if self.collapse_blank_lines {
+            content = Cow::Owned(collapse_blank_lines(&content));
+        }
+        content
+    }
+}
+
+/// Turns every run of blank lines into a single empty line and drops blank
+/// lines at the start and end of the file.
+pub fn collapse_blank_lines(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut previous_blank = true;
+    let mut last_len = 0;
+
+    for line in content.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        last_len = out.len();
+        if blank {
+            out.push_str(if line.ends_with("\r\n") { "\r\n" } else { "\n" });
+        } else {
+            out.push_str(line);
+        }
+        previous_blank = blank;
+    }
+    if previous_blank {
+        out.truncate(last_len);
+    }
+    out
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Comment Stripping
+// /////////////////////////////////////////////////////////////////////////////
+
+// Each stripper walks the source once, skipping over string literals so that
+// comment markers inside them are left alone. When the lexer is unsure it
+// leans towards keeping text: strings that are not closed end at the line
+// break, so a misread quote can only cause a comment to survive, never code
+// to be dropped.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Rust,
+    JavaScript,
+    Go,
+    C,
+    Cpp,
+    CSharp,
+    /// Languages with `"""` text blocks; `nested` when block comments nest.
+    TextBlocks {
+        nested: bool,
+    },
+    Python,
+    Shell,
+    Toml,
+    Yaml,
+}
+
+fn dialect_for(language: &str) -> Option<Dialect> {
+    Some(match language {
+        "rust" => Dialect::Rust,
+        "typescript" | "tsx" | "javascript" | "jsx" => Dialect::JavaScript,
+        "go" => Dialect::Go,
+        "c" | "objectivec" => Dialect::C,
+        "cpp" => Dialect::Cpp,
+        "csharp" => Dialect::CSharp,
+        "java" | "groovy" => Dialect::TextBlocks { nested: false },
+        "kotlin" | "scala" | "swift" | "dart" => Dialect::TextBlocks { nested: true },
+        "python" => Dialect::Python,
+        "bash" | "fish" => Dialect::Shell,
+        "toml" => Dialect::Toml,
+        "yaml" => Dialect::Yaml,
+        _ => return None,
+    })
+}
+
+fn strip_comments(src: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Python => strip_python(src),
+        Dialect::Shell => strip_shell(src),
+        Dialect::Toml => strip_toml(src),
+        Dialect::Yaml => strip_yaml(src),
+        _ => strip_c_like(src, dialect),
+    }
+}
+
+/// Copies the source to the output except for the ranges passed to
+/// `strip`. Lines that lost a comment have their trailing whitespace
+/// trimmed, and are dropped entirely when nothing else is left on them.
+struct Stripper<'a> {
+    src: &'a str,
+    out: String,
+    copied: usize,
+    line: usize,
+    touched: Vec<usize>,
+}
+
+impl<'a> Stripper<'a> {
+    fn new(src: &'a str) -> Self {
+        Stripper {
+            src,
+            out: String::with_capacity(src.len()),
+            copied: 0,
+            line: 0,
+            touched: Vec::new(),
+        }
+    }
+
+    fn flush(&mut self, to: usize) {
+        let text = &self.src[self.copied..to];
+        self.line += text.bytes().filter(|&b| b == b'\n').count();
+        self.out.push_str(text);
+        self.copied = to;
+    }
+
+    fn strip(&mut self, from: usize, mut to: usize) {
+        self.flush(from);
+        if self.touched.last() != Some(&self.line) {
+            self.touched.push(self.line);
+        }
+        let b = self.src.as_bytes();
+        // A line comment ends at the `\n`; the `\r` before it is part of
+        // the line break.
+        if to > from && b.get(to) == Some(&b'\n') && b[to - 1] == b'\r' {
+            to -= 1;
+        }
+        let before = self.out.bytes().last();
+        if before.is_none_or(|c| c == b' ' || c == b'\t' || c == b'\n') {
+            while b.get(to).is_some_and(|&c| c == b' ' || c == b'\t') {
+                to += 1;
+            }
+        } else if before.is_some_and(is_ident) && b.get(to).copied().is_some_and(is_ident) {
+            // `a/* */b` must not turn into `ab`.
+            self.out.push(' ');
+        }
+        self.copied = to;
+    }
+
+    fn finish(mut self) -> String {
+        self.flush(self.src.len());
+
+        let mut result = String::with_capacity(self.out.len());
+        let mut touched = self.touched.iter().peekable();
+        for (n, line) in self.out.split_inclusive('\n').enumerate() {
+            if touched.next_if(|&&t| t == n).is_none() {
+                result.push_str(line);
+                continue;
+            }
+            let body = line.trim_end();
+            if body.trim_start().is_empty() {
+                continue;
+            }
+            result.push_str(body);
+            if line.ends_with("\r\n") {
+                result.push_str("\r\n");
+            } else if line.ends_with('\n') {
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+fn line_end(b: &[u8], from: usize) -> usize {
+    b[from..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(b.len(), |p| from + p)
+}
+
+/// End of a string opened by the quote at `start`. Single-line strings stop
+/// at an unescaped line break.
+fn quoted_end(b: &[u8], start: usize, quote: u8, multiline: bool) -> usize {
+    let mut i = start + 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            b'\n' if !multiline => return i,
+            _ => i += 1,
+        }
+    }
+    b.len()
+}
+
+/// End of a literal that closes with `close`, scanning from `from`.
+fn delimited_end(b: &[u8], from: usize, close: &[u8], escapes: bool) -> usize {
+    let mut i = from;
+    while i < b.len() {
+        if escapes && b[i] == b'\\' {
+            i += 2;
+        } else if b[i..].starts_with(close) {
+            return i + close.len();
+        } else {
+            i += 1;
+        }
+    }
+    b.len()
+}
+
+fn strip_c_like(src: &str, dialect: Dialect) -> String {
+    let b = src.as_bytes();
+    let mut s = Stripper::new(src);
+    let nested = matches!(
+        dialect,
+        Dialect::Rust | Dialect::TextBlocks { nested: true }
+    );
+    let mut i = 0;
+
+    while i < b.len() {
+        let next = b.get(i + 1).copied();
+        i = match (b[i], dialect) {
+            (b'/', _) if next == Some(b'/') => {
+                let end = line_end(b, i);
+                s.strip(i, end);
+                end
+            }
+            (b'/', _) if next == Some(b'*') => {
+                let end = block_comment_end(b, i, nested);
+                s.strip(i, end);
+                end
+            }
+            (b'"', Dialect::TextBlocks { .. }) if b[i..].starts_with(b"\"\"\"") => {
+                delimited_end(b, i + 3, b"\"\"\"", true)
+            }
+            (b'"', Dialect::Rust) => quoted_end(b, i, b'"', true),
+            (b'"', _) => quoted_end(b, i, b'"', false),
+            (b'\'', Dialect::Rust) => rust_quote_end(src, i),
+            (b'\'', _) => quoted_end(b, i, b'\'', false),
+            (b'`', Dialect::JavaScript) => template_end(b, i),
+            (b'`', Dialect::Go) => delimited_end(b, i + 1, b"`", false),
+            (b'r', Dialect::Rust) => rust_raw_string_end(b, i).unwrap_or(i + 1),
+            (b'R', Dialect::Cpp) if next == Some(b'"') && !preceded_by_ident(b, i) => {
+                cpp_raw_string_end(b, i + 1)
+            }
+            (b'@', Dialect::CSharp) => verbatim_string_end(b, i).unwrap_or(i + 1),
+            (b'/', Dialect::JavaScript) if regex_allowed(b, i) => regex_end(b, i),
+            _ => i + 1,
+        };
+    }
+    s.finish()
+}
+
+fn block_comment_end(b: &[u8], start: usize, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < b.len() {
+        if b[i..].starts_with(b"/*") && (nested || depth == 0) {
+            depth += 1;
+            i += 2;
+        } else if b[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    b.len()
+}
+
+fn preceded_by_ident(b: &[u8], i: usize) -> bool {
+    i > 0 && is_ident(b[i - 1])
+}
+
+/// A quote in Rust is either a char literal or a lifetime / loop label.
+fn rust_quote_end(src: &str, start: usize) -> usize {
+    let b = src.as_bytes();
+    if b.get(start + 1) == Some(&b'\\') {
+        return quoted_end(b, start, b'\'', false);
+    }
+    match src[start + 1..].chars().next() {
+        Some(c) if b.get(start + 1 + c.len_utf8()) == Some(&b'\'') => start + 2 + c.len_utf8(),
+        _ => start + 1,
+    }
+}
+
+/// `r"…"`, `r#"…"#`, `br"…"` and `cr"…"`.
+fn rust_raw_string_end(b: &[u8], start: usize) -> Option<usize> {
+    let prefix_start = match start.checked_sub(1).map(|p| b[p]) {
+        Some(b'b') | Some(b'c') => start - 1,
+        _ => start,
+    };
+    if preceded_by_ident(b, prefix_start) {
+        return None;
+    }
+    let hashes = b[start + 1..].iter().take_while(|&&c| c == b'#').count();
+    let quote = start + 1 + hashes;
+    if b.get(quote) != Some(&b'"') {
+        return None;
+    }
+    let mut close = vec![b'"'];
+    close.extend(std::iter::repeat_n(b'#', hashes));
+    Some(delimited_end(b, quote + 1, &close, false))
+}
+
+/// `R"delim(…)delim"`, starting at the quote.
+fn cpp_raw_string_end(b: &[u8], quote: usize) -> usize {
+    let Some(open) = b[quote + 1..].iter().position(|&c| c == b'(') else {
+        return quote + 1;
+    };
+    let delimiter = &b[quote + 1..quote + 1 + open];
+    if delimiter.len() > 16 || delimiter.iter().any(|c| c.is_ascii_whitespace()) {
+        return quote + 1;
+    }
+    let mut close = vec![b')'];
+    close.extend_from_slice(delimiter);
+    close.push(b'"');
+    delimited_end(b, quote + open + 2, &close, false)
+}
+
+/// `@"…"`, `@$"…"` and `$@"…"`, where `""` is an escaped quote.
+fn verbatim_string_end(b: &[u8], at: usize) -> Option<usize> {
+    let quote = match (b.get(at + 1), b.get(at + 2)) {
+        (Some(b'"'), _) => at + 1,
+        (Some(b'$'), Some(b'"')) => at + 2,
+        _ => return None,
+    };
+    let mut i = quote + 1;
+    while i < b.len() {
+        if b[i] == b'"' {
+            if b.get(i + 1) == Some(&b'"') {
+                i += 2;
+                continue;
+            }
+            return Some(i + 1);
+        }
+        i += 1;
+    }
+    Some(b.len())
+}
+
+fn template_end(b: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 2,
+            b'`' => return i + 1,
+            b'$' if b.get(i + 1) == Some(&b'{') => i = template_expression_end(b, i + 2),
+            _ => i += 1,
+        }
+    }
+    b.len()
+}
+
+fn template_expression_end(b: &[u8], from: usize) -> usize {
+    let mut depth = 1;
+    let mut i = from;
+    while i < b.len() {
+        match b[i] {
+            b'{' => {
+                depth += 1;
+                i += 1;
+            }
+            b'}' => {
+                depth -= 1;
+                i += 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            b'`' => i = template_end(b, i),
+            b'"' | b'\'' => i = quoted_end(b, i, b[i], false),
+            _ => i += 1,
+        }
+    }
+    b.len()
+}
+
+const REGEX_KEYWORDS: &[&[u8]] = &[
+    b"return",
+    b"typeof",
+    b"case",
+    b"do",
+    b"else",
+    b"in",
+    b"of",
+    b"void",
+    b"yield",
+    b"await",
+    b"throw",
+    b"delete",
+    b"instanceof",
+    b"new",
+];
+
+/// A `/` starts a regex literal where an expression is expected, and is a
+/// division everywhere else.
+fn regex_allowed(b: &[u8], slash: usize) -> bool {
+    let Some(prev) = b[..slash].iter().rposition(|c| !c.is_ascii_whitespace()) else {
+        return true;
+    };
+    if b"(,=:[!&|?{};+-*%<>~^".contains(&b[prev]) {
+        return true;
+    }
+    if !is_ident(b[prev]) {
+        return false;
+    }
+    let word_start = b[..=prev]
+        .iter()
+        .rposition(|&c| !is_ident(c))
+        .map_or(0, |p| p + 1);
+    REGEX_KEYWORDS.contains(&&b[word_start..=prev])
+}
+
+fn regex_end(b: &[u8], start: usize) -> usize {
+    let mut in_class = false;
+    let mut i = start + 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 2,
+            b'[' => {
+                in_class = true;
+                i += 1;
+            }
+            b']' => {
+                in_class = false;
+                i += 1;
+            }
+            b'/' if !in_class => return i + 1,
+            b'\n' => return start + 1,
+            _ => i += 1,
+        }
+    }
+    start + 1
+}
+
+fn strip_python(src: &str) -> String {
+    let b = src.as_bytes();
+    let mut s = Stripper::new(src);
+    let mut i = 0;
+
+    while i < b.len() {
+        i = match b[i] {
+            b'#' if i == 0 && b.get(1) == Some(&b'!') => line_end(b, i),
+            b'#' => {
+                let end = line_end(b, i);
+                s.strip(i, end);
+                end
+            }
+            quote @ (b'"' | b'\'') => {
+                let triple = [quote; 3];
+                let end = if b[i..].starts_with(&triple) {
+                    delimited_end(b, i + 3, &triple, true)
+                } else {
+                    quoted_end(b, i, quote, false)
+                };
+                if let Some(from) = docstring_start(b, i, end) {
+                    s.strip(from, end);
+                }
+                end
+            }
+            _ => i + 1,
+        };
+    }
+    s.finish()
+}
+
+/// Start of the docstring the string at `quote..end` is, if it is one: a
+/// string statement of its own that opens a module, or directly follows a
+/// `def`/`class` header and is not the only statement in that body.
+fn docstring_start(b: &[u8], quote: usize, end: usize) -> Option<usize> {
+    let line_start = b[..quote]
+        .iter()
+        .rposition(|&c| c == b'\n')
+        .map_or(0, |p| p + 1);
+    let indent = b[line_start..quote]
+        .iter()
+        .take_while(|&&c| c == b' ' || c == b'\t')
+        .count();
+    let prefix = &b[line_start + indent..quote];
+    if prefix.len() > 2 || !prefix.iter().all(|c| b"rRuU".contains(c)) {
+        return None;
+    }
+
+    let rest = &b[end..line_end(b, end)];
+    let rest = rest.trim_ascii();
+    if !rest.is_empty() && rest[0] != b'#' {
+        return None;
+    }
+
+    match previous_code_byte(b, line_start) {
+        None => return Some(line_start + indent),
+        Some(b':') => {}
+        Some(_) => return None,
+    }
+
+    let mut pos = line_end(b, end);
+    while pos < b.len() {
+        let start = pos + 1;
+        pos = line_end(b, start);
+        let line = &b[start..pos];
+        let code = line.trim_ascii_start();
+        if code.is_empty() || code[0] == b'#' {
+            continue;
+        }
+        let next_indent = line.len() - code.len();
+        return (next_indent >= indent).then_some(line_start + indent);
+    }
+    None
+}
+
+/// Last non-blank byte before `line_start`, skipping blank and comment-only
+/// lines.
+fn previous_code_byte(b: &[u8], line_start: usize) -> Option<u8> {
+    b[..line_start]
+        .split(|&c| c == b'\n')
+        .rev()
+        .map(|line| line.trim_ascii())
+        .find(|line| !line.is_empty() && line[0] != b'#')
+        .and_then(|line| line.last().copied())
+}
+
+fn strip_shell(src: &str) -> String {
+    let b = src.as_bytes();
+    let mut s = Stripper::new(src);
+    let mut heredocs: Vec<(&[u8], bool)> = Vec::new();
+    let mut i = 0;
+
+    while i < b.len() {
+        i = match b[i] {
+            b'\\' => i + 2,
+            b'\'' if i > 0 && b[i - 1] == b'$' => quoted_end(b, i, b'\'', true),
+            b'\'' => delimited_end(b, i + 1, b"'", false),
+            b'"' => quoted_end(b, i, b'"', true),
+            b'#' if i == 0 && b.get(1) == Some(&b'!') => line_end(b, i),
+            b'#' if i == 0 || b" \t\n;&|(".contains(&b[i - 1]) => {
+                let end = line_end(b, i);
+                s.strip(i, end);
+                end
+            }
+            b'<' if b[i..].starts_with(b"<<") && !b[i..].starts_with(b"<<<") => {
+                match heredoc_word(b, i + 2) {
+                    Some((word, strip_tabs, end)) => {
+                        heredocs.push((word, strip_tabs));
+                        end
+                    }
+                    None => i + 2,
+                }
+            }
+            b'\n' if !heredocs.is_empty() => {
+                let end = heredoc_bodies_end(b, i + 1, &heredocs);
+                heredocs.clear();
+                end
+            }
+            _ => i + 1,
+        };
+    }
+    s.finish()
+}
+
+/// The terminator of a heredoc redirection whose operator ends at `from`.
+fn heredoc_word(b: &[u8], from: usize) -> Option<(&[u8], bool, usize)> {
+    let mut i = from;
+    let strip_tabs = b.get(i) == Some(&b'-');
+    if strip_tabs {
+        i += 1;
+    }
+    while b.get(i).is_some_and(|&c| c == b' ' || c == b'\t') {
+        i += 1;
+    }
+    let quote = b.get(i).copied().filter(|c| *c == b'\'' || *c == b'"');
+    if quote.is_some() {
+        i += 1;
+    }
+    let start = i;
+    while b
+        .get(i)
+        .is_some_and(|&c| !c.is_ascii_whitespace() && !b"'\";&|<>()".contains(&c))
+    {
+        i += 1;
+    }
+    let word = &b[start..i];
+    if !word
+        .first()
+        .is_some_and(|&c| c.is_ascii_alphabetic() || c == b'_')
+    {
+        return None;
+    }
+    if quote.is_some() && b.get(i) == quote.as_ref() {
+        i += 1;
+    }
+    Some((word, strip_tabs, i))
+}
+
+fn heredoc_bodies_end(b: &[u8], from: usize, heredocs: &[(&[u8], bool)]) -> usize {
+    let mut pos = from;
+    for (word, strip_tabs) in heredocs {
+        while pos < b.len() {
+            let end = line_end(b, pos);
+            let mut line = &b[pos..end];
+            if line.last() == Some(&b'\r') {
+                line = &line[..line.len() - 1];
+            }
+            if *strip_tabs {
+                let tabs = line.iter().take_while(|&&c| c == b'\t').count();
+                line = &line[tabs..];
+            }
+            pos = (end + 1).min(b.len());
+            if line == *word {
+                break;
+            }
+        }
+    }
+    pos
+}
+
+fn strip_toml(src: &str) -> String {
+    let b = src.as_bytes();
+    let mut s = Stripper::new(src);
+    let mut i = 0;
+
+    while i < b.len() {
+        i = match b[i] {
+            b'#' => {
+                let end = line_end(b, i);
+                s.strip(i, end);
+                end
+            }
+            b'"' if b[i..].starts_with(b"\"\"\"") => delimited_end(b, i + 3, b"\"\"\"", true),
+            b'"' => quoted_end(b, i, b'"', false),
+            b'\'' if b[i..].starts_with(b"'''") => delimited_end(b, i + 3, b"'''", false),
+            b'\'' => {
+                let end = line_end(b, i);
+                b[i + 1..end]
+                    .iter()
+                    .position(|&c| c == b'\'')
+                    .map_or(end, |p| i + p + 2)
+            }
+            _ => i + 1,
+        };
+    }
+    s.finish()
+}
+
+fn strip_yaml(src: &str) -> String {
+    let mut s = Stripper::new(src);
+    let mut block_indent: Option<usize> = None;
+    let mut offset = 0;
+
+    for line in src.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let b = line.trim_end_matches(['\r', '\n']).as_bytes();
+        let indent = b.iter().take_while(|&&c| c == b' ').count();
+
+        // Block scalars (`key: |`) hold text where `#` is not a comment.
+        if let Some(parent) = block_indent {
+            if b.trim_ascii().is_empty() || indent > parent {
+                continue;
+            }
+            block_indent = None;
+        }
+
+        let mut comment = None;
+        let mut j = 0;
+        while j < b.len() {
+            j = match b[j] {
+                b'#' if j == 0 || b[j - 1] == b' ' || b[j - 1] == b'\t' => {
+                    comment = Some(j);
+                    break;
+                }
+                quote @ (b'"' | b'\'') if j == 0 || b" \t[{,:-?".contains(&b[j - 1]) => {
+                    yaml_quoted_end(b, j, quote)
+                }
+                _ => j + 1,
+            };
+        }
+
+        let code = &b[..comment.unwrap_or(b.len())];
+        if let Some(comment) = comment {
+            s.strip(start + comment, start + b.len());
+        }
+        if is_block_scalar_header(code) {
+            block_indent = Some(indent);
+        }
+    }
+    s.finish()
+}
+
+fn yaml_quoted_end(b: &[u8], start: usize, quote: u8) -> usize {
+    if quote == b'"' {
+        return quoted_end(b, start, quote, false);
+    }
+    let mut i = start + 1;
+    while i < b.len() {
+        if b[i] == b'\'' {
+            if b.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    b.len()
+}
+
+fn is_block_scalar_header(code: &[u8]) -> bool {
+    let code = code.trim_ascii_end();
+    let indicators = code
+        .iter()
+        .rev()
+        .take_while(|c| c.is_ascii_digit() || **c == b'-' || **c == b'+')
+        .count();
+    let code = &code[..code.len() - indicators];
+    match code.split_last() {
+        Some((b'|' | b'>', rest)) => rest.is_empty() || rest.ends_with(b" "),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_like_comments_are_stripped_and_emptied_lines_dropped() {
+        let src =
+            "// header\nint a = 1; // trailing\n/* block\n   spans */\nint b/* inline */= 2;\n";
+        assert_eq!(strip_comments(src, Dialect::C), "int a = 1;\nint b= 2;\n");
+    }
+
+    #[test]
+    fn comment_markers_inside_strings_are_kept() {
+        let src = "let url = \"http://example.com\"; // link\nlet s = '/*';\n";
+        assert_eq!(
+            strip_comments(src, Dialect::JavaScript),
+            "let url = \"http://example.com\";\nlet s = '/*';\n"
+        );
+    }
+
+    #[test]
+    fn identifiers_around_an_inline_comment_stay_apart() {
+        assert_eq!(strip_comments("a/* */b\n", Dialect::C), "a b\n");
+    }
+
+    #[test]
+    fn rust_raw_strings_lifetimes_and_nested_comments() {
+        let src = "fn f<'a>(s: &'a str) -> &'a str { s } /* outer /* inner */ still */\nlet r = r#\"// not \"a\" comment\"#;\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Rust),
+            "fn f<'a>(s: &'a str) -> &'a str { s }\nlet r = r#\"// not \"a\" comment\"#;\n"
+        );
+    }
+
+    #[test]
+    fn javascript_templates_and_regexes_are_not_comments() {
+        let src =
+            "const t = `${a // b}`;\nconst re = /\\/\\/+/g; // slashes\nconst half = x / 2 / y;\n";
+        assert_eq!(
+            strip_comments(src, Dialect::JavaScript),
+            "const t = `${a // b}`;\nconst re = /\\/\\/+/g;\nconst half = x / 2 / y;\n"
+        );
+    }
+
+    #[test]
+    fn python_docstrings_and_comments_are_stripped() {
+        let src = "#!/usr/bin/env python\n\"\"\"Module doc.\"\"\"\n\ndef f():\n    \"\"\"Doc.\"\"\"\n    x = \"# not a comment\"  # comment\n    return x\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Python),
+            "#!/usr/bin/env python\n\ndef f():\n    x = \"# not a comment\"\n    return x\n"
+        );
+    }
+
+    #[test]
+    fn python_docstring_that_is_the_whole_body_is_kept() {
+        let src = "def f():\n    \"\"\"Only a docstring.\"\"\"\n\nx = 1\n";
+        assert_eq!(strip_comments(src, Dialect::Python), src);
+    }
+
+    #[test]
+    fn shell_heredocs_and_parameter_hashes_are_kept() {
+        let src = "# setup\necho ${#list} $#  # count\ncat <<EOF\n# kept\nEOF\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Shell),
+            "echo ${#list} $#\ncat <<EOF\n# kept\nEOF\n"
+        );
+    }
+
+    #[test]
+    fn yaml_block_scalars_and_quotes_are_kept() {
+        let src = "# top\nkey: \"a # b\" # note\nscript: |\n  # kept\n  run\nurl: http://x/#frag\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Yaml),
+            "key: \"a # b\"\nscript: |\n  # kept\n  run\nurl: http://x/#frag\n"
+        );
+    }
+
+    #[test]
+    fn toml_strings_are_kept() {
+        let src = "# top\nname = \"a#b\" # note\npath = 'c:\\#'\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Toml),
+            "name = \"a#b\"\npath = 'c:\\#'\n"
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings_survive_stripping() {
+        assert_eq!(
+            strip_comments("a(); // x\r\nb();\r\n", Dialect::C),
+            "a();\r\nb();\r\n"
+        );
+    }
+
+    #[test]
+    fn python_docstrings_are_stripped_from_crlf_files() {
+        let src = "def f():\r\n    \"\"\"Doc.\"\"\"\r\n    return 1\r\n";
+        assert_eq!(
+            strip_comments(src, Dialect::Python),
+            "def f():\r\n    return 1\r\n"
+        );
+    }
+
+    #[test]
+    fn blank_line_runs_collapse_to_one() {
+        assert_eq!(collapse_blank_lines("\n\na\n\n\n  \nb\n\n"), "a\n\nb\n");
+    }
+
+    #[test]
+    fn skeleton_mode_falls_back_to_full_content() {
+        let options = ParseOptions {
+            content_mode: ContentMode::Skeleton,
+            content_mode_rules: vec![ContentModeRule {
+                pattern: "src/core".to_string(),
+                mode: ContentMode::Full,
+            }],
+            ..Default::default()
+        };
+        let transform = Transform::new(&options)
+            .unwrap()
+            .with_root(Path::new("/repo"));
+        let rust = "pub fn a() {\n    1;\n}\n";
+
+        assert_eq!(
+            transform.apply(Path::new("/repo/src/a.rs"), rust),
+            "pub fn a() { ... }\n"
+        );
+        assert_eq!(
+            transform.apply(Path::new("/repo/src/core/a.rs"), rust),
+            rust
+        );
+        assert_eq!(
+            transform.apply(Path::new("/repo/notes.txt"), "text\n"),
+            "text\n"
+        );
+        let broken = "pub fn a( {\n";
+        assert_eq!(transform.apply(Path::new("/repo/src/b.rs"), broken), broken);
+    }
+}
@@ -1,33 +1,54 @@
-use anyhow::{self, Context, Result};
+use anyhow::{self, Result};
 use chrono::{DateTime, Local};
-use content_inspector::{inspect, ContentType};
 use dirs;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::{self, File},
-    io::{self, Read, Write},
+    io::{self, Write},
+    ops::Range,
     path::{Path, PathBuf},
     process::Command,
+    sync::{Mutex, MutexGuard},
 };
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-use tauri::{App, Runtime, AppHandle, Emitter, Manager};
+use tauri::{App, Runtime, Emitter, Manager};
 use uuid::Uuid;
 
+use crate::cache;
+use crate::cancel::{CancelToken, ParseRegistry};
+use crate::chunks::{ChunkBudget, ChunkInfo, ChunkWriter};
+use crate::engine;
+use crate::forge;
+use crate::git;
+use crate::output::{self, ContentOutput, OutputFormat};
+use crate::progress::{ParsePhase, ProgressSink, ProgressTracker};
+use crate::report::ParseReport;
+use crate::summary::SourceInfo;
+use crate::tokenizer::TokenizerKind;
+use crate::transform::{ContentMode, ContentModeRule, Transform};
+use crate::walk::{self, IgnoreRules, ParseRoot, PathFilter, SortOrder};
+
 pub const APP_NAME: &str = "parser-ai";
 pub const PARSED_FILES_DIR: &str = "parsed-files";
 pub const CONTENT_FILENAME: &str = "content.txt";
+pub const CONTENT_JSONL_FILENAME: &str = "content.jsonl";
 pub const METADATA_FILENAME: &str = "metadata.json";
 pub const TREE_FILENAME: &str = "tree.json";
+pub const REPORT_FILENAME: &str = "report.json";
 pub const TEMP_REPOS_DIR: &str = "temp-repos";
+pub const DEFAULT_HEADER_TEMPLATE: &str = "===== {path} =====";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadata {
     pub path: String,
     pub name: String,
     pub size: u64,
+    /// Encoding the file was transcoded from, `None` in parses made before
+    /// encodings were recorded.
+    #[serde(default)]
+    pub encoding: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +61,86 @@ pub struct ParseMetadata {
     pub files_count: usize,
     pub total_size: u64,
     pub remote_url: String,
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(default)]
+    pub header_template: Option<String>,
+    #[serde(default)]
+    pub total_tokens: u64,
+    #[serde(default)]
+    pub tokenizer: TokenizerKind,
+    #[serde(default)]
+    pub chunks: Vec<ChunkInfo>,
+    #[serde(default)]
+    pub limited_files: Vec<LimitedFile>,
+    /// Commit the parsed git checkout was at.
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Branch or tag of that commit, if it has one.
+    #[serde(default)]
+    pub git_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitReason {
+    MaxFileSize,
+    MaxTotalSize,
+}
+
+/// A file that was left out of the output or cut short by a size limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitedFile {
+    pub path: String,
+    pub size: u64,
+    pub reason: LimitReason,
+    /// Number of lines kept when the file was truncated instead of skipped.
+    pub truncated_to_lines: Option<usize>,
+    /// Bytes kept when the file size limit cut a truncated file short
+    /// before it reached the line limit.
+    #[serde(default)]
+    pub truncated_to_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub format: OutputFormat,
+    /// Line written before each file in the plain format, `{path}` is
+    /// replaced by the file path.
+    pub header_template: Option<String>,
+    /// Also write `content.jsonl` with one record per file.
+    pub jsonl: bool,
+    /// Prefix output paths with the name of the folder or repository they
+    /// are relative to.
+    pub prefix_root_name: bool,
+    /// Start the output with a summary and a directory tree of the parsed files.
+    pub include_summary: bool,
+    pub tokenizer: TokenizerKind,
+    /// Also split the output into `content-001.txt`, `content-002.txt`, ...
+    /// that each stay within the budget.
+    pub chunk_budget: Option<ChunkBudget>,
+    /// Remove comments from source files in the languages `transform` knows.
+    pub strip_comments: bool,
+    /// Collapse runs of blank lines into one.
+    pub collapse_blank_lines: bool,
+    /// Files larger than this many bytes are skipped, or truncated when
+    /// `truncate_lines` is set.
+    pub max_file_size: Option<u64>,
+    /// Files that would take the parse over this many bytes are skipped.
+    pub max_total_size: Option<u64>,
+    /// Keep the first N lines of files over `max_file_size` instead of
+    /// skipping them, and never more than `max_file_size` bytes of them.
+    pub truncate_lines: Option<usize>,
+    pub content_mode: ContentMode,
+    /// Per-subtree overrides of `content_mode`, matched against paths
+    /// relative to the parse root. The last matching rule wins.
+    pub content_mode_rules: Vec<ContentModeRule>,
+    /// Order of the entries within each directory, in the output and the
+    /// tree alike.
+    pub sort_order: SortOrder,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,11 +150,22 @@ pub enum ParsedPath {
         name: String,
         path: String,
         size: u64,
+        #[serde(default)]
+        tokens: u64,
+        /// Encoding the file was read in, only known for parsed files.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encoding: Option<String>,
+        /// Where the file's content lies in `content.txt`, only known for
+        /// parsed files and cleared when `content.txt` is edited.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_range: Option<Range<u64>>,
     },
     Directory {
         name: String,
         path: String,
         size: u64,
+        #[serde(default)]
+        tokens: u64,
         children: Vec<ParsedPath>,
     },
 }
@@ -65,6 +177,62 @@ impl ParsedPath {
             ParsedPath::Directory { path, .. } => path,
         }
     }
+
+    pub fn tokens(&self) -> u64 {
+        match self {
+            ParsedPath::File { tokens, .. } | ParsedPath::Directory { tokens, .. } => *tokens,
+        }
+    }
+
+    /// Fills in the encodings of files from `file_encodings`, keyed by path.
+    pub fn apply_encodings(&mut self, file_encodings: &HashMap<String, String>) {
+        match self {
+            ParsedPath::File { path, encoding, .. } => {
+                *encoding = file_encodings.get(path.as_str()).cloned();
+            }
+            ParsedPath::Directory { children, .. } => {
+                for child in children.iter_mut() {
+                    child.apply_encodings(file_encodings);
+                }
+            }
+        }
+    }
+
+    /// Fills in the content ranges of files from `file_ranges`, keyed by path.
+    pub fn apply_content_ranges(&mut self, file_ranges: &HashMap<String, Range<u64>>) {
+        match self {
+            ParsedPath::File {
+                path,
+                content_range,
+                ..
+            } => {
+                *content_range = file_ranges.get(path.as_str()).cloned();
+            }
+            ParsedPath::Directory { children, .. } => {
+                for child in children.iter_mut() {
+                    child.apply_content_ranges(file_ranges);
+                }
+            }
+        }
+    }
+
+    /// Fills in token counts of files from `file_tokens`, keyed by path, and
+    /// sums them up for directories.
+    pub fn apply_tokens(&mut self, file_tokens: &HashMap<String, u64>) {
+        match self {
+            ParsedPath::File { path, tokens, .. } => {
+                *tokens = file_tokens.get(path.as_str()).copied().unwrap_or(0);
+            }
+            ParsedPath::Directory {
+                tokens, children, ..
+            } => {
+                for child in children.iter_mut() {
+                    child.apply_tokens(file_tokens);
+                }
+                *tokens = children.iter().map(ParsedPath::tokens).sum();
+            }
+        }
+    }
 }
 
 // /////////////////////////////////////////////////////////////////////////////
@@ -84,11 +252,115 @@ pub fn init_app_structure() -> Result<()> {
 }
 
 pub fn get_app_dir() -> Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(dir) = TEST_APP_DIR.with(|dir| dir.borrow().clone()) {
+        return Ok(dir);
+    }
     Ok(dirs::home_dir()
         .ok_or(anyhow::anyhow!("No home dir"))?
         .join(APP_NAME))
 }
 
+#[cfg(test)]
+thread_local! {
+    static TEST_APP_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Points `get_app_dir` at a temporary directory on the current thread for
+/// as long as the guard lives.
+#[cfg(test)]
+pub struct TestAppDir {
+    pub dir: tempfile::TempDir,
+}
+
+#[cfg(test)]
+impl TestAppDir {
+    pub fn new() -> Self {
+        let dir = tempfile::TempDir::new().unwrap();
+        TEST_APP_DIR.with(|app_dir| *app_dir.borrow_mut() = Some(dir.path().to_path_buf()));
+        TestAppDir { dir }
+    }
+}
+
+#[cfg(test)]
+impl Default for TestAppDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestAppDir {
+    fn drop(&mut self) {
+        TEST_APP_DIR.with(|app_dir| *app_dir.borrow_mut() = None);
+    }
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// Store Files
+// /////////////////////////////////////////////////////////////////////////////
+
+/// Serializes the read-modify-write cycles on one of the app's store files,
+/// such as the profiles or the cache index.
+#[derive(Default)]
+pub struct StoreLock(Mutex<()>);
+
+impl StoreLock {
+    pub const fn new() -> Self {
+        StoreLock(Mutex::new(()))
+    }
+
+    /// A panic while the lock was held leaves the file as it was, so a
+    /// poisoned lock is still usable.
+    pub fn lock(&self) -> MutexGuard<'_, ()> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Replaces `path` with `contents` through a temporary file next to it, so
+/// the file is never left half written.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    replace_file(path, contents, |path| File::create(path))
+}
+
+/// Like `write_atomic`, but the file is only readable by the user.
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    replace_file(path, contents, create_private)
+}
+
+fn replace_file(path: &Path, contents: &[u8], create: fn(&Path) -> io::Result<File>) -> Result<()> {
+    let mut tmp_name = path
+        .file_name()
+        .ok_or(anyhow::anyhow!("Failed to extract filename"))?
+        .to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+fn create_private(path: &Path) -> io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+    }
+    #[cfg(not(unix))]
+    {
+        File::create(path)
+    }
+}
+
 pub fn get_parse_dir(dir_name: &str) -> Result<PathBuf> {
     Ok(get_app_dir()?.join(PARSED_FILES_DIR).join(dir_name))
 }
@@ -97,56 +369,58 @@ pub fn get_content_path(parse_dir: &Path) -> PathBuf {
     parse_dir.join(CONTENT_FILENAME)
 }
 
+/// Path of chunk `index` (1-based) as recorded in the parse's manifest.
+pub fn get_chunk_path(parse_dir: &Path, index: usize) -> Result<PathBuf> {
+    let metadata = load_metadata(parse_dir)?;
+    let chunk = metadata
+        .chunks
+        .iter()
+        .find(|chunk| chunk.index == index)
+        .ok_or_else(|| anyhow::anyhow!("Chunk {} does not exist", index))?;
+    Ok(parse_dir.join(&chunk.file_name))
+}
+
 pub fn get_metadata_path(parse_dir: &Path) -> PathBuf {
     parse_dir.join(METADATA_FILENAME)
 }
 
-fn create_parse_directory(remote_url: &str) -> Result<(PathBuf, File, String)> {
+/// Creates the directory of a new parse. Its id ends in a random suffix, so
+/// parses started within the same second still get ids of their own, which
+/// `ParseRegistry` relies on.
+fn create_parse_directory(remote_url: &str) -> Result<(PathBuf, String)> {
     let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let suffix = Uuid::new_v4().simple().to_string();
+    let stamp = format!("{}_{}", timestamp, &suffix[..8]);
 
     let parse_id = if !remote_url.is_empty() {
         let safe_name = sanitize_repo_url(remote_url);
-        format!("{}_{}", safe_name, timestamp)
+        format!("{}_{}", safe_name, stamp)
     } else {
-        timestamp
+        stamp
     };
 
-    let parse_dir = get_app_dir()?.join(PARSED_FILES_DIR).join(&parse_id);
-    fs::create_dir_all(&parse_dir)?;
+    let parsed_files_dir = get_app_dir()?.join(PARSED_FILES_DIR);
+    fs::create_dir_all(&parsed_files_dir)?;
+    // `create_dir` fails rather than reuse a directory that already exists.
+    let parse_dir = parsed_files_dir.join(&parse_id);
+    fs::create_dir(&parse_dir)?;
 
-    let content_path = parse_dir.join(CONTENT_FILENAME);
-    let content_file = File::create(&content_path)?;
-    Ok((parse_dir, content_file, parse_id))
+    Ok((parse_dir, parse_id))
 }
 
 // /////////////////////////////////////////////////////////////////////////////
 // Git & Remote Handling
 // /////////////////////////////////////////////////////////////////////////////
 
-pub fn clone_git_repo(url: &str) -> Result<PathBuf> {
-    let app_dir = get_app_dir()?;
-    let temp_dir = app_dir.join(TEMP_REPOS_DIR);
-    if !temp_dir.exists() {
-        fs::create_dir_all(&temp_dir)?;
-    }
-
-    let folder_name = format!("{}_{}", Uuid::new_v4(), "repo");
-    let target_path = temp_dir.join(&folder_name);
-
-    let status = Command::new("git")
-        .args(["clone", "--depth", "1", url, target_path.to_str().unwrap()])
-        .status()
-        .map_err(|e| anyhow::anyhow!("Failed to execute git clone: {}", e))?;
-
-    if !status.success() {
-        return Err(anyhow::anyhow!("Git clone failed"));
-    }
-
-    Ok(target_path)
-}
-
 fn sanitize_repo_url(input: &str) -> String {
-    let clean_url = input
+    // Tree and blob URLs are named after the repository, ref and subpath,
+    // without the forge's `tree`/`blob` markers in between.
+    let repo_url = forge::parse_repo_url(input);
+    let name = match &repo_url.ref_path {
+        Some(ref_path) => format!("{}/{}", repo_url.clone_url, ref_path),
+        None => repo_url.clone_url,
+    };
+    let clean_url = name
         .trim_start_matches("https://")
         .trim_start_matches("http://");
 
@@ -168,6 +442,45 @@ fn sanitize_repo_url(input: &str) -> String {
     }
 }
 
+pub fn strip_url_credentials(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let host = authority
+                .rsplit_once('@')
+                .map_or(authority, |(_, host)| host);
+            if path.is_empty() {
+                format!("{}://{}", scheme, host)
+            } else {
+                format!("{}://{}/{}", scheme, host, path)
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Checkout root of the cloned repository `path` lies in, if any.
+fn temp_repo_root(path: &Path) -> Option<PathBuf> {
+    let temp_repos_root = get_app_dir().ok()?.join(TEMP_REPOS_DIR);
+    let mut components = path.strip_prefix(&temp_repos_root).ok()?.components();
+    let clone_dir = components.next()?;
+    let checkout = components.next()?;
+    Some(
+        temp_repos_root
+            .join(clone_dir.as_os_str())
+            .join(checkout.as_os_str()),
+    )
+}
+
+/// Directory each of `paths` is parsed relative to, see `walk::resolve_roots`.
+/// Inputs in a cloned repository are parsed relative to its checkout.
+pub fn resolve_parse_roots(paths: &[String]) -> Vec<PathBuf> {
+    walk::resolve_roots(paths)
+        .into_iter()
+        .map(|root| temp_repo_root(&root).unwrap_or(root))
+        .collect()
+}
+
 fn cleanup_temp_repos(paths: &[String]) -> Result<()> {
     let app_dir = get_app_dir()?;
     let temp_repos_root = app_dir.join(TEMP_REPOS_DIR);
@@ -195,70 +508,92 @@ fn cleanup_temp_repos(paths: &[String]) -> Result<()> {
         if let Err(e) = fs::remove_dir_all(&root) {
             eprintln!("Failed to remove temp repo {:?}: {}", root, e);
         }
+        cache::release_checkout(&root);
     }
 
     Ok(())
 }
 
+/// Deletes the cloned repositories among a parse's inputs once the parse is
+/// done with them, whether it succeeded, failed or was cancelled.
+struct TempRepoCleanup<'a> {
+    paths: &'a [String],
+}
+
+impl Drop for TempRepoCleanup<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = cleanup_temp_repos(self.paths) {
+            eprintln!("Failed to clean up temp repos: {}", e);
+        }
+    }
+}
+
 // /////////////////////////////////////////////////////////////////////////////
 // Main Parsing Logic
 // /////////////////////////////////////////////////////////////////////////////
 
+/// Parses `paths` into a new parse directory and returns its metadata.
+/// Progress goes to `sink`, and the parse can be cancelled through
+/// `registry` with the id passed to `ProgressSink::started`.
 pub fn parse_files(
     paths: Vec<String>,
-    app: AppHandle,
     remote_url: Option<String>,
+    options: ParseOptions,
+    sink: &dyn ProgressSink,
+    registry: &ParseRegistry,
 ) -> Result<ParseMetadata> {
-    let remote_url_str = remote_url.unwrap_or_default();
-    let (parse_dir, mut output_file, parse_id) = create_parse_directory(&remote_url_str)?;
-
-    let total_files = count_text_files(&paths)?;
-    emit_progress(&app, &parse_id, 0, total_files, None);
-
-    let mut parsed_files = Vec::new();
-    let mut total_size = 0u64;
-    let mut current_count = 0;
-    let mut file_tree = Vec::new();
-
-    for path_str in &paths {
-        let path = Path::new(path_str);
-        if path.exists() {
-            if path.is_symlink() {
-                continue;
-            }
-            file_tree.push(build_file_tree(&path)?);
-        }
+    let _temp_repos = TempRepoCleanup { paths: &paths };
+    let filter = PathFilter::new(&options.include, &options.exclude)?;
+    let transform = Transform::new(&options)?;
+
+    // A token pasted into the URL must not end up in the parse id or metadata.
+    let remote_url_str = remote_url
+        .map(|url| strip_url_credentials(&url))
+        .unwrap_or_default();
+    let (parse_dir, parse_id) = create_parse_directory(&remote_url_str)?;
+    let mut active = ActiveParse::start(sink, registry, &parse_id, &parse_dir);
+
+    let roots: Vec<ParseRoot> = resolve_parse_roots(&paths)
+        .iter()
+        .map(|root| {
+            ParseRoot::new(root, &filter, options.prefix_root_name)
+                .with_transform(&transform)
+                .with_cancel(&active.cancel)
+        })
+        .collect();
+    let mut output = ContentOutput::new(&get_content_path(&parse_dir), &options)?;
+    if options.jsonl {
+        output = output.with_jsonl(File::create(parse_dir.join(CONTENT_JSONL_FILENAME))?);
+    }
+    if let Some(budget) = options.chunk_budget {
+        output = output.with_chunks(ChunkWriter::new(&parse_dir, budget, &options)?);
     }
 
-    for path_str in &paths {
-        let path = Path::new(&path_str);
-        if path.is_symlink() {
-            continue;
-        }
+    let progress = ProgressTracker::new(&parse_id, sink);
+    let result = engine::run(&paths, &roots, &options, &mut output, &progress)?;
+    progress.set_phase(ParsePhase::Finalizing);
 
-        if path.is_dir() {
-            process_directory_with_progress(
-                &path,
-                &mut output_file,
-                &mut parsed_files,
-                &mut total_size,
-                &mut current_count,
-                total_files,
-                &app,
-                &parse_id,
-            )?;
-        } else if process_single_text_file(
-            &path,
-            &mut output_file,
-            &mut parsed_files,
-            &mut total_size,
-        )? {
-            current_count += 1;
-            emit_progress(&app, &parse_id, current_count, total_files, None);
-        }
-    }
+    let source = match roots.first() {
+        Some(root) => git::read_source_info(&root.path, &remote_url_str),
+        None => SourceInfo::default(),
+    };
+    let stats = output.finish(&source)?;
 
-    cleanup_temp_repos(&paths)?;
+    let file_tokens: HashMap<String, u64> = stats
+        .files
+        .iter()
+        .map(|file| (file.path.clone(), file.tokens))
+        .collect();
+    let file_ranges: HashMap<String, Range<u64>> = stats
+        .files
+        .iter()
+        .map(|file| (file.path.clone(), file.content_range.clone()))
+        .collect();
+    let mut file_tree = result.tree;
+    for node in &mut file_tree {
+        node.apply_tokens(&file_tokens);
+        node.apply_content_ranges(&file_ranges);
+    }
 
     let now = Local::now();
 
@@ -269,127 +604,115 @@ pub fn parse_files(
         remote_url: remote_url_str,
         created_at: now,
         updated_at: now,
-        files_count: parsed_files.len(),
-        total_size,
+        files_count: result.files.len(),
+        total_size: result.total_size,
+        format: options.format,
+        header_template: options.header_template.clone(),
+        total_tokens: stats.total_tokens,
+        tokenizer: options.tokenizer,
+        chunks: stats.chunks,
+        limited_files: result.limited_files,
+        commit: source.commit.clone(),
+        git_ref: source.git_ref.clone(),
     };
 
     let metadata_path = parse_dir.join(METADATA_FILENAME);
     let meta_file = File::create(&metadata_path)?;
     serde_json::to_writer_pretty(meta_file, &metadata)?;
 
-    let tree_path = parse_dir.join(TREE_FILENAME);
-    let tree_file = File::create(&tree_path)?;
-    serde_json::to_writer_pretty(tree_file, &file_tree)?;
+    let report = ParseReport {
+        skipped: result.skipped,
+    };
+    let report_file = File::create(parse_dir.join(REPORT_FILENAME))?;
+    serde_json::to_writer_pretty(report_file, &report)?;
+
+    save_tree(&parse_dir, &file_tree)?;
 
     let content_path = get_content_path(&parse_dir);
-    emit_progress(
-        &app,
-        &parse_id,
-        total_files,
-        total_files,
-        Some(content_path.display().to_string()),
-    );
+    progress.finish(&content_path.display().to_string());
 
+    active.finish();
     Ok(metadata)
 }
 
-fn process_directory_with_progress(
-    dir: &Path,
-    output_file: &mut File,
-    parsed_files: &mut Vec<FileMetadata>,
-    total_size: &mut u64,
-    current_count: &mut usize,
-    total_files: usize,
-    app: &AppHandle,
-    parse_id: &str,
-) -> Result<()> {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
-            if path.is_symlink() || file_name.starts_with('.') {
-                continue;
-            }
+/// Makes a running parse cancellable through `cancel_parse`. Dropping it
+/// without `finish` after the parse was cancelled deletes the partially
+/// written parse directory and reports it to the sink.
+struct ActiveParse<'a> {
+    sink: &'a dyn ProgressSink,
+    registry: &'a ParseRegistry,
+    parse_id: String,
+    parse_dir: PathBuf,
+    cancel: CancelToken,
+    finished: bool,
+}
 
-            if path.is_dir() {
-                let _ = process_directory_with_progress(
-                    &path,
-                    output_file,
-                    parsed_files,
-                    total_size,
-                    current_count,
-                    total_files,
-                    app,
-                    parse_id,
-                );
-            } else {
-                if let Ok(processed) =
-                    process_single_text_file(&path, output_file, parsed_files, total_size)
-                {
-                    if processed {
-                        *current_count += 1;
-                        emit_progress(app, parse_id, *current_count, total_files, None);
-                    }
-                }
-            }
+impl<'a> ActiveParse<'a> {
+    fn start(
+        sink: &'a dyn ProgressSink,
+        registry: &'a ParseRegistry,
+        parse_id: &str,
+        parse_dir: &Path,
+    ) -> Self {
+        let cancel = registry.register(parse_id);
+        // The id is needed to cancel, and the first progress event only
+        // comes once all files are counted.
+        sink.started(parse_id);
+        ActiveParse {
+            sink,
+            registry,
+            parse_id: parse_id.to_string(),
+            parse_dir: parse_dir.to_path_buf(),
+            cancel,
+            finished: false,
         }
     }
-    Ok(())
+
+    fn finish(&mut self) {
+        self.finished = true;
+    }
 }
 
-fn process_single_text_file(
-    path: &Path,
-    output_file: &mut File,
-    parsed_files: &mut Vec<FileMetadata>,
-    total_size: &mut u64,
-) -> Result<bool> {
-    if !is_text_file(path) {
-        return Ok(false);
-    }
-
-    match write_file_content(path, output_file) {
-        Ok(_) => {
-            if let Ok(metadata) = get_file_metadata(path) {
-                *total_size += metadata.size;
-                parsed_files.push(metadata);
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+impl Drop for ActiveParse<'_> {
+    fn drop(&mut self) {
+        self.registry.remove(&self.parse_id);
+        if self.finished || !self.cancel.is_cancelled() {
+            return;
         }
-        Err(e) => {
-            eprintln!("Skipping file due to read/write error: {:?} - {}", path, e);
-            Ok(false)
+        if let Err(e) = fs::remove_dir_all(&self.parse_dir) {
+            eprintln!(
+                "Failed to remove cancelled parse {:?}: {}",
+                self.parse_dir, e
+            );
         }
+        self.sink.cancelled(&self.parse_id);
     }
 }
 
 // /////////////////////////////////////////////////////////////////////////////
 // File System & Tree Building
 // /////////////////////////////////////////////////////////////////////////////
-fn get_recursive_dir_size(path: &Path) -> u64 {
+fn get_recursive_dir_size(path: &Path, rules: &IgnoreRules, filter: &PathFilter) -> u64 {
     let mut total_size = 0;
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let child_path = entry.path();
-
-            // Skip symlinks to avoid infinite loops
-            if child_path.is_symlink() {
-                continue;
-            }
-
-            if child_path.is_dir() {
-                total_size += get_recursive_dir_size(&child_path);
-            } else if let Ok(metadata) = fs::metadata(&child_path) {
-                total_size += metadata.len();
-            }
+    // Symlinks are never returned here, which avoids infinite loops
+    for child_path in walk::read_dir_filtered(path, rules, filter) {
+        if child_path.is_dir() {
+            let rules = rules.descend(&child_path);
+            total_size += get_recursive_dir_size(&child_path, &rules, filter);
+        } else if let Ok(metadata) = fs::metadata(&child_path) {
+            total_size += metadata.len();
         }
     }
     total_size
 }
 
-fn build_file_tree(path: &Path) -> Result<ParsedPath> {
+/// Preview of `path` with its direct children, leaving out what a parse with
+/// `filter` would and listing the rest in `order`.
+pub fn build_file_tree_shallow(
+    path: &Path,
+    filter: &PathFilter,
+    order: SortOrder,
+) -> Result<ParsedPath> {
     let name = path
         .file_name()
         .ok_or(anyhow::anyhow!("Failed to extract filename"))?
@@ -398,97 +721,58 @@ fn build_file_tree(path: &Path) -> Result<ParsedPath> {
     let file_path = path.to_string_lossy().to_string();
 
     if path.is_dir() {
-        let mut children = Vec::new();
-        let mut total_size = 0u64;
-
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                let child_path = entry.path();
-                if child_path.is_symlink() {
-                    continue;
-                }
+        let children = shallow_children(path, filter, order)?;
+        let current_level_size = children
+            .iter()
+            .map(|child| match child {
+                ParsedPath::File { size, .. } | ParsedPath::Directory { size, .. } => *size,
+            })
+            .sum();
 
-                if let Ok(tree) = build_file_tree(&child_path) {
-                    total_size += match &tree {
-                        ParsedPath::File { size, .. } => *size,
-                        ParsedPath::Directory { size, .. } => *size,
-                    };
-                    children.push(tree);
-                }
-            }
-        }
         Ok(ParsedPath::Directory {
             name,
             children,
             path: file_path,
-            size: total_size,
+            size: current_level_size,
+            tokens: 0,
         })
     } else {
         let metadata = get_file_metadata(path)?;
-        let size = if is_text_file(path) { metadata.size } else { 0 };
-
         Ok(ParsedPath::File {
             name,
-            size,
+            size: metadata.size,
             path: file_path,
+            tokens: 0,
+            encoding: None,
+            content_range: None,
         })
     }
 }
 
-pub fn build_file_tree_shallow(path: &Path) -> Result<ParsedPath> {
-    let name = path
-        .file_name()
-        .ok_or(anyhow::anyhow!("Failed to extract filename"))?
-        .to_string_lossy()
-        .to_string();
-    let file_path = path.to_string_lossy().to_string();
-
-    if path.is_dir() {
-        let mut children = Vec::new();
-        let mut current_level_size = 0u64;
-
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                let child_path = entry.path();
-                if child_path.is_symlink() {
-                    continue;
-                }
-
-                let child_node = create_shallow_node(&child_path)?;
-
-                match &child_node {
-                    ParsedPath::File { size, .. } | ParsedPath::Directory { size, .. } => {
-                        current_level_size += *size;
-                    }
-                }
-
-                children.push(child_node);
-            }
-        }
-
-        children.sort_by(|a, b| match (a, b) {
-            (ParsedPath::Directory { .. }, ParsedPath::File { .. }) => std::cmp::Ordering::Less,
-            (ParsedPath::File { .. }, ParsedPath::Directory { .. }) => std::cmp::Ordering::Greater,
-            _ => a.path().cmp(b.path()),
-        });
-
-        Ok(ParsedPath::Directory {
-            name,
-            children,
-            path: file_path,
-            size: current_level_size,
-        })
-    } else {
-        let metadata = get_file_metadata(path)?;
-        Ok(ParsedPath::File {
-            name,
-            size: metadata.size,
-            path: file_path,
+/// Shallow nodes for the children of `dir` that a parse with `filter` would
+/// visit, in `order`.
+pub fn shallow_children(
+    dir: &Path,
+    filter: &PathFilter,
+    order: SortOrder,
+) -> Result<Vec<ParsedPath>> {
+    let listing = walk::read_dir_classified(dir, &IgnoreRules::for_dir(dir), filter)?;
+    let mut entries: Vec<(PathBuf, bool)> = listing
+        .children
+        .into_iter()
+        .map(|path| {
+            let is_dir = path.is_dir();
+            (path, is_dir)
         })
-    }
+        .collect();
+    order.sort(&mut entries);
+    entries
+        .iter()
+        .map(|(path, _)| create_shallow_node(path, filter))
+        .collect()
 }
 
-pub fn create_shallow_node(path: &Path) -> Result<ParsedPath> {
+pub fn create_shallow_node(path: &Path, filter: &PathFilter) -> Result<ParsedPath> {
     let name = path
         .file_name()
         .ok_or(anyhow::anyhow!("Failed to extract filename"))?
@@ -497,12 +781,13 @@ pub fn create_shallow_node(path: &Path) -> Result<ParsedPath> {
     let file_path = path.to_string_lossy().to_string();
 
     if path.is_dir() {
-        let size = get_recursive_dir_size(path);
+        let size = get_recursive_dir_size(path, &IgnoreRules::for_dir(path), filter);
 
         Ok(ParsedPath::Directory {
             name,
             path: file_path,
             size,
+            tokens: 0,
             children: Vec::new(),
         })
     } else {
@@ -511,6 +796,9 @@ pub fn create_shallow_node(path: &Path) -> Result<ParsedPath> {
             name,
             path: file_path,
             size: metadata.len(),
+            tokens: 0,
+            encoding: None,
+            content_range: None,
         })
     }
 }
@@ -536,11 +824,16 @@ pub fn to_shallow_node(node: &ParsedPath) -> ParsedPath {
     match node {
         ParsedPath::File { .. } => node.clone(),
         ParsedPath::Directory {
-            name, path, size, ..
+            name,
+            path,
+            size,
+            tokens,
+            ..
         } => ParsedPath::Directory {
             name: name.clone(),
             path: path.clone(),
             size: *size,
+            tokens: *tokens,
             children: Vec::new(),
         },
     }
@@ -562,15 +855,37 @@ pub fn load_tree(parse_dir: &Path) -> Result<Vec<ParsedPath>> {
     Ok(serde_json::from_reader(io::BufReader::new(file))?)
 }
 
+/// Parses made before reports existed have none, which reads as an empty
+/// report.
+pub fn load_report(parse_dir: &Path) -> Result<ParseReport> {
+    let path = parse_dir.join(REPORT_FILENAME);
+    if !path.exists() {
+        return Ok(ParseReport::default());
+    }
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(io::BufReader::new(file))?)
+}
+
 pub fn load_content(parse_dir: &Path) -> Result<String> {
     Ok(fs::read_to_string(get_content_path(parse_dir))?)
 }
 
+pub fn load_chunk(parse_dir: &Path, index: usize) -> Result<String> {
+    Ok(fs::read_to_string(get_chunk_path(parse_dir, index)?)?)
+}
+
 pub fn update_content(parse_dir: &Path, content: &str) -> Result<()> {
     let content_path = get_content_path(parse_dir);
     let mut file = File::create(content_path)?;
     file.write_all(content.as_bytes())?;
 
+    // The recorded content ranges no longer match the edited content.
+    let mut tree = load_tree(parse_dir)?;
+    for node in &mut tree {
+        node.apply_content_ranges(&HashMap::new());
+    }
+    save_tree(parse_dir, &tree)?;
+
     let mut metadata = load_metadata(parse_dir)?;
     metadata.updated_at = Local::now();
     save_metadata(&get_metadata_path(parse_dir), &metadata)?;
@@ -578,29 +893,73 @@ pub fn update_content(parse_dir: &Path, content: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn save_metadata(path: &Path, metadata: &ParseMetadata) -> Result<()> {
-    let file = File::create(path)?;
-    let writer = io::BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, metadata)?;
-    Ok(())
-}
+/// Returns the path of `content.jsonl`, rebuilding it from `content.txt` for
+/// parses that were made without the JSONL option.
+pub fn export_jsonl(parse_dir: &Path) -> Result<PathBuf> {
+    let jsonl_path = parse_dir.join(CONTENT_JSONL_FILENAME);
+    if jsonl_path.exists() {
+        return Ok(jsonl_path);
+    }
 
-fn write_file_content(path: &Path, output_file: &mut File) -> Result<()> {
-    let mut file = File::open(&path).with_context(|| format!("Opening {}", path.display()))?;
-    let mut content = String::new();
+    let metadata = load_metadata(parse_dir)?;
+    let content = load_content(parse_dir)?;
+    let mut files = Vec::new();
+    collect_files(&load_tree(parse_dir)?, &mut files);
 
-    file.read_to_string(&mut content)
-        .with_context(|| format!("File is not valid UTF-8: {}", path.display()))?;
+    let ranges: Option<Vec<(String, Range<u64>)>> = files
+        .iter()
+        .map(|(path, range)| Some((path.clone(), range.clone()?)))
+        .collect();
+    let split =
+        ranges.and_then(|ranges| output::split_content_at(&content, metadata.format, &ranges));
+    let records = match split {
+        Some(records) => records,
+        None => {
+            let known_paths: HashSet<String> = files.into_iter().map(|(path, _)| path).collect();
+            output::split_content(
+                &content,
+                metadata.format,
+                metadata.header_template.as_deref(),
+                &known_paths,
+            )
+        }
+    };
 
-    writeln!(output_file, "===== {} =====", path.display())?;
+    let mut writer = io::BufWriter::new(File::create(&jsonl_path)?);
+    for record in &records {
+        output::write_record(&mut writer, record)?;
+    }
+    writer.flush()?;
 
-    output_file.write_all(content.as_bytes())?;
+    Ok(jsonl_path)
+}
 
-    writeln!(output_file)?;
+fn collect_files(tree: &[ParsedPath], files: &mut Vec<(String, Option<Range<u64>>)>) {
+    for node in tree {
+        match node {
+            ParsedPath::File {
+                path,
+                content_range,
+                ..
+            } => files.push((path.clone(), content_range.clone())),
+            ParsedPath::Directory { children, .. } => collect_files(children, files),
+        }
+    }
+}
 
+pub fn save_metadata(path: &Path, metadata: &ParseMetadata) -> Result<()> {
+    let file = File::create(path)?;
+    let writer = io::BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, metadata)?;
     Ok(())
 }
 
+pub fn save_tree(parse_dir: &Path, tree: &[ParsedPath]) -> Result<()> {
+    let file = File::create(parse_dir.join(TREE_FILENAME))?;
+    let writer = io::BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, tree)?;
+    Ok(())
+}
 
 // /////////////////////////////////////////////////////////////////////////////
 // System Actions (Open, Reveal)
@@ -647,80 +1006,6 @@ fn reveal_in_folder(path: &Path) -> Result<()> {
 // Helper Utils (Private)
 // /////////////////////////////////////////////////////////////////////////////
 
-fn emit_progress(
-    app: &AppHandle,
-    parse_id: &str,
-    current: usize,
-    total: usize,
-    file_path: Option<String>,
-) {
-    let progress = if total > 0 {
-        (current as f32 / total as f32) * 100.0
-    } else {
-        0.0
-    };
-    let _ = app.emit(
-        "parse-progress",
-        json!({
-            "parse_id": parse_id,
-            "parse_progress": progress,
-            "files_amount": total,
-            "result_file_path": file_path.unwrap_or(serde_json::Value::Null.to_string()),
-        }),
-    );
-}
-
-fn count_text_files(paths: &[String]) -> Result<usize> {
-    let mut count = 0;
-    for path_str in paths {
-        let path = Path::new(path_str);
-        if path.is_dir() {
-            count += count_text_files_in_dir(path)?;
-        } else if is_text_file(path) {
-            count += 1;
-        }
-    }
-    Ok(count)
-}
-
-fn count_text_files_in_dir(dir: &Path) -> Result<usize> {
-    let mut count = 0;
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                count += count_text_files_in_dir(&path)?;
-            } else if is_text_file(&path) {
-                count += 1;
-            }
-        }
-    }
-    Ok(count)
-}
-
-fn is_text_file(path: &Path) -> bool {
-    if !path.is_file() {
-        return false;
-    }
-
-    let mut file = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return false,
-    };
-
-    let mut buf = [0u8; 8192];
-    let sample_len = match file.read(&mut buf) {
-        Ok(len) => len,
-        Err(_) => return false,
-    };
-
-    if sample_len == 0 {
-        return true;
-    }
-
-    !matches!(inspect(&buf[..sample_len]), ContentType::BINARY)
-}
-
 fn get_file_metadata(path: &Path) -> Result<FileMetadata> {
     let metadata = fs::metadata(path)?;
     Ok(FileMetadata {
@@ -731,6 +1016,7 @@ fn get_file_metadata(path: &Path) -> Result<FileMetadata> {
             .to_string_lossy()
             .to_string(),
         size: metadata.len(),
+        encoding: None,
     })
 }
 
@@ -785,4 +1071,145 @@ pub fn setup_tray<R: Runtime>(app: &App<R>) -> Result<(), tauri::Error> {
         .build(app)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::Cancelled;
+    use crate::progress::{ChannelSink, ParseEvent, ParseProgress};
+
+    /// Cancels the parse as soon as it is announced.
+    struct CancellingSink<'a> {
+        registry: &'a ParseRegistry,
+        inner: ChannelSink,
+    }
+
+    impl ProgressSink for CancellingSink<'_> {
+        fn started(&self, parse_id: &str) {
+            self.inner.started(parse_id);
+            assert!(self.registry.cancel(parse_id));
+        }
+
+        fn progress(&self, progress: &ParseProgress) {
+            self.inner.progress(progress);
+        }
+
+        fn cancelled(&self, parse_id: &str) {
+            self.inner.cancelled(parse_id);
+        }
+    }
+
+    #[test]
+    fn cancelled_parses_leave_nothing_behind() {
+        let app_dir = TestAppDir::new();
+        let clone_dir = app_dir.dir.path().join(TEMP_REPOS_DIR).join("repo-clone");
+        let checkout = clone_dir.join("repo");
+        fs::create_dir_all(&checkout).unwrap();
+        fs::write(checkout.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let registry = ParseRegistry::default();
+        let (inner, events) = ChannelSink::new();
+        let sink = CancellingSink {
+            registry: &registry,
+            inner,
+        };
+        let paths = vec![checkout.to_string_lossy().to_string()];
+        let err = parse_files(paths, None, ParseOptions::default(), &sink, &registry).unwrap_err();
+
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+        let parsed_files_dir = app_dir.dir.path().join(PARSED_FILES_DIR);
+        assert_eq!(fs::read_dir(parsed_files_dir).unwrap().count(), 0);
+        assert!(!clone_dir.exists());
+
+        let events: Vec<ParseEvent> = events.try_iter().collect();
+        let Some(ParseEvent::Started(parse_id)) = events.first() else {
+            panic!("parse was not announced");
+        };
+        assert!(matches!(events.last(), Some(ParseEvent::Cancelled(id)) if id == parse_id));
+        assert!(!registry.cancel(parse_id));
+    }
+
+    fn child_names(node: &ParsedPath) -> Vec<&str> {
+        let ParsedPath::Directory { children, .. } = node else {
+            panic!("{} is not a directory", node.path());
+        };
+        children
+            .iter()
+            .map(|child| match child {
+                ParsedPath::File { name, .. } | ParsedPath::Directory { name, .. } => name.as_str(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn previews_leave_out_what_the_filter_excludes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::create_dir(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("src/notes.md"), "notes\n").unwrap();
+        fs::write(dir.path().join("target/out.rs"), "fn out() {}\n").unwrap();
+
+        let filter = PathFilter::new(&[], &["target/".to_string(), "*.md".to_string()])
+            .unwrap()
+            .with_root(dir.path());
+        let tree = build_file_tree_shallow(dir.path(), &filter, SortOrder::default()).unwrap();
+        assert_eq!(child_names(&tree), ["src"]);
+
+        let ParsedPath::Directory { children, .. } = &tree else {
+            unreachable!();
+        };
+        let ParsedPath::Directory { size, .. } = children[0] else {
+            panic!("src is not a directory");
+        };
+        assert_eq!(size, "fn main() {}\n".len() as u64);
+    }
+
+    #[test]
+    fn preview_children_follow_the_sort_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("b_dir")).unwrap();
+        fs::write(dir.path().join("b_dir/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("a.rs"), "").unwrap();
+        fs::write(dir.path().join("c.rs"), "").unwrap();
+        let filter = PathFilter::default();
+
+        let tree = build_file_tree_shallow(dir.path(), &filter, SortOrder::DirectoriesFirst);
+        assert_eq!(child_names(&tree.unwrap()), ["b_dir", "a.rs", "c.rs"]);
+        let tree = build_file_tree_shallow(dir.path(), &filter, SortOrder::Lexical);
+        assert_eq!(child_names(&tree.unwrap()), ["a.rs", "b_dir", "c.rs"]);
+
+        let children = shallow_children(dir.path(), &filter, SortOrder::DirectoriesFirst).unwrap();
+        let names: Vec<&str> = children.iter().map(|child| child.path()).collect();
+        assert!(names[0].ends_with("b_dir"));
+        let children = shallow_children(dir.path(), &filter, SortOrder::Lexical).unwrap();
+        let names: Vec<&str> = children.iter().map(|child| child.path()).collect();
+        assert!(names[0].ends_with("a.rs") && names[1].ends_with("b_dir"));
+    }
+
+    #[test]
+    fn store_files_are_replaced_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+
+        let private_path = dir.path().join("store.enc");
+        write_private(&private_path, b"secret").unwrap();
+        assert_eq!(fs::read(&private_path).unwrap(), b"secret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&private_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["store.enc", "store.json"]);
+    }
+}
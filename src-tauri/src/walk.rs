@@ -0,0 +1,603 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use crate::cancel::CancelToken;
+use crate::report::SkipReason;
+use crate::transform::Transform;
+
+// Within a single directory `.ignore` takes precedence over `.gitignore`,
+// so it is pushed last and therefore checked first.
+const IGNORE_FILENAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Clone)]
+pub struct IgnoreRules {
+    levels: Vec<Arc<Gitignore>>,
+    exclude: Option<Arc<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Rules that apply to the entries inside `dir`, including ignore files
+    /// found in its ancestors up to the enclosing git repository root.
+    /// Outside a repository only `dir`'s own ignore files apply, so an
+    /// ignore file higher up (e.g. `~/.gitignore`) cannot filter the parse.
+    pub fn for_dir(dir: &Path) -> Self {
+        let git_root = find_git_root(dir);
+
+        let mut rules = IgnoreRules {
+            levels: Vec::new(),
+            exclude: git_root.as_deref().and_then(load_git_exclude),
+        };
+
+        let root = git_root.as_deref().unwrap_or(dir);
+        let mut ancestors: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|p| p.starts_with(root))
+            .collect();
+        ancestors.reverse();
+
+        for ancestor in ancestors {
+            rules.push_dir(ancestor);
+        }
+        rules
+    }
+
+    /// Rules for the entries inside `dir`, which must be a child of the
+    /// directory these rules were built for.
+    pub fn descend(&self, dir: &Path) -> Self {
+        if dir.join(".git").exists() {
+            return IgnoreRules::for_dir(dir);
+        }
+        let mut rules = self.clone();
+        rules.push_dir(dir);
+        rules
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let matchers = self
+            .levels
+            .iter()
+            .rev()
+            .chain(self.exclude.iter())
+            .map(|m| m.as_ref())
+            .chain(std::iter::once(global_gitignore()));
+
+        for matcher in matchers {
+            match matcher.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    fn push_dir(&mut self, dir: &Path) {
+        for filename in IGNORE_FILENAMES {
+            let ignore_path = dir.join(filename);
+            if !ignore_path.is_file() {
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new(dir);
+            if let Some(e) = builder.add(&ignore_path) {
+                eprintln!("Failed to read {:?}: {}", ignore_path, e);
+            }
+            if let Ok(matcher) = builder.build() {
+                if !matcher.is_empty() {
+                    self.levels.push(Arc::new(matcher));
+                }
+            }
+        }
+    }
+}
+
+/// User supplied include/exclude globs, matched against paths relative to
+/// the parse root. A leading `!` moves a pattern to the opposite list, and a
+/// pattern without a `/` matches the file name at any depth.
+#[derive(Clone, Default)]
+pub struct PathFilter {
+    root: PathBuf,
+    include: Option<Arc<PatternSet>>,
+    exclude: Option<Arc<PatternSet>>,
+}
+
+pub struct PatternSet {
+    names: GlobSet,
+    paths: GlobSet,
+    /// Patterns written with a trailing `/`, which only match directories.
+    dir_names: GlobSet,
+    dir_paths: GlobSet,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut included = Vec::new();
+        let mut excluded = Vec::new();
+
+        for pattern in include {
+            match pattern.strip_prefix('!') {
+                Some(negated) => excluded.push(negated),
+                None => included.push(pattern.as_str()),
+            }
+        }
+        for pattern in exclude {
+            match pattern.strip_prefix('!') {
+                Some(negated) => included.push(negated),
+                None => excluded.push(pattern.as_str()),
+            }
+        }
+
+        Ok(PathFilter {
+            root: PathBuf::new(),
+            include: PatternSet::build(&included)?.map(Arc::new),
+            exclude: PatternSet::build(&excluded)?.map(Arc::new),
+        })
+    }
+
+    pub fn with_root(&self, root: &Path) -> Self {
+        PathFilter {
+            root: root.to_path_buf(),
+            ..self.clone()
+        }
+    }
+
+    pub fn allows(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(relative, is_dir) {
+                return false;
+            }
+        }
+        // Directories are never dropped for failing to match an include
+        // pattern, otherwise `src/**/*.rs` could not reach into `src`.
+        match &self.include {
+            Some(include) if !is_dir => include.is_match(relative, false),
+            _ => true,
+        }
+    }
+}
+
+impl PatternSet {
+    pub fn build(patterns: &[&str]) -> Result<Option<Self>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        let mut dir_names = GlobSetBuilder::new();
+        let mut dir_paths = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            let pattern = pattern.trim().trim_start_matches('/');
+            // As in `.gitignore`, `docs/` matches the directory `docs` only.
+            let (pattern, dir_only) = match pattern.strip_suffix('/') {
+                Some(dir) => (dir, true),
+                None => (pattern, false),
+            };
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+
+            match (pattern.contains('/'), dir_only) {
+                (true, false) => paths.add(glob),
+                (false, false) => names.add(glob),
+                (true, true) => dir_paths.add(glob),
+                (false, true) => dir_names.add(glob),
+            };
+        }
+
+        Ok(Some(PatternSet {
+            names: names.build()?,
+            paths: paths.build()?,
+            dir_names: dir_names.build()?,
+            dir_paths: dir_paths.build()?,
+        }))
+    }
+
+    fn is_match(&self, relative: &Path, is_dir: bool) -> bool {
+        let name = relative.file_name();
+        let name_matches = name.is_some_and(|name| self.names.is_match(name));
+        if name_matches || self.paths.is_match(relative) {
+            return true;
+        }
+        is_dir
+            && (name.is_some_and(|name| self.dir_names.is_match(name))
+                || self.dir_paths.is_match(relative))
+    }
+
+    /// Whether the file `relative` or one of the directories it lies in
+    /// matches.
+    pub fn is_match_within(&self, relative: &Path) -> bool {
+        relative
+            .ancestors()
+            .take_while(|p| !p.as_os_str().is_empty())
+            .enumerate()
+            .any(|(depth, p)| self.is_match(p, depth > 0))
+    }
+}
+
+/// The directory a parse input belongs to. Paths written to the output are
+/// reported relative to it, optionally prefixed with its folder name.
+pub struct ParseRoot {
+    pub path: PathBuf,
+    pub filter: PathFilter,
+    pub transform: Transform,
+    pub cancel: CancelToken,
+    prefix: Option<String>,
+}
+
+impl ParseRoot {
+    pub fn new(path: &Path, filter: &PathFilter, prefix_name: bool) -> Self {
+        let prefix = if prefix_name {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        } else {
+            None
+        };
+        ParseRoot {
+            path: path.to_path_buf(),
+            filter: filter.with_root(path),
+            transform: Transform::default(),
+            cancel: CancelToken::default(),
+            prefix,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: &Transform) -> Self {
+        self.transform = transform.with_root(&self.path);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: &CancelToken) -> Self {
+        self.cancel = cancel.clone();
+        self
+    }
+
+    pub fn display_path(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        let mut display = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if let Some(prefix) = &self.prefix {
+            display = if display.is_empty() {
+                prefix.clone()
+            } else {
+                format!("{}/{}", prefix, display)
+            };
+        }
+        // The root itself still needs a name when it is parsed directly.
+        if display.is_empty() {
+            display = self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.to_string_lossy().to_string());
+        }
+        display
+    }
+}
+
+/// Directory that include/exclude patterns are resolved against for each
+/// input: the deepest directory shared by all inputs, or the input's own
+/// directory when they have nothing in common (e.g. different drives).
+pub fn resolve_roots(paths: &[String]) -> Vec<PathBuf> {
+    let own_dirs: Vec<PathBuf> = paths
+        .iter()
+        .map(|p| {
+            let path = Path::new(p);
+            if path.is_dir() {
+                path.to_path_buf()
+            } else {
+                path.parent().map(Path::to_path_buf).unwrap_or_default()
+            }
+        })
+        .collect();
+
+    let common = own_dirs.split_first().and_then(|(first, rest)| {
+        first
+            .ancestors()
+            .find(|candidate| rest.iter().all(|dir| dir.starts_with(candidate)))
+            .filter(|candidate| !candidate.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+    });
+
+    match common {
+        Some(root) => vec![root; paths.len()],
+        None => own_dirs,
+    }
+}
+
+/// Order the entries of each directory are parsed in, which is also the
+/// order they appear in the output and the tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Subdirectories before files, each sorted by name.
+    #[default]
+    DirectoriesFirst,
+    /// Sorted by name only, directories mixed in with files.
+    Lexical,
+}
+
+impl SortOrder {
+    /// Sorts `(path, is_dir)` pairs. Names are compared byte-wise, so the
+    /// order does not depend on the platform or locale.
+    pub fn sort(self, entries: &mut [(PathBuf, bool)]) {
+        entries.sort_by(|(a, a_is_dir), (b, b_is_dir)| {
+            let by_kind = match self {
+                SortOrder::DirectoriesFirst => b_is_dir.cmp(a_is_dir),
+                SortOrder::Lexical => Ordering::Equal,
+            };
+            by_kind.then_with(|| a.file_name().cmp(&b.file_name()))
+        });
+    }
+}
+
+/// Lists the children of `dir` that take part in a parse: symlinks, hidden
+/// entries, anything matched by the ignore rules and anything rejected by
+/// `filter` are left out.
+pub fn read_dir_filtered(dir: &Path, rules: &IgnoreRules, filter: &PathFilter) -> Vec<PathBuf> {
+    read_dir_classified(dir, rules, filter)
+        .map(|listing| listing.children)
+        .unwrap_or_default()
+}
+
+pub struct DirListing {
+    pub children: Vec<PathBuf>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+/// Like `read_dir_filtered`, but also reports why each left out entry was
+/// skipped.
+pub fn read_dir_classified(
+    dir: &Path,
+    rules: &IgnoreRules,
+    filter: &PathFilter,
+) -> io::Result<DirListing> {
+    let mut listing = DirListing {
+        children: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        match skip_reason(&path, rules, filter) {
+            Some(reason) => listing.skipped.push((path, reason)),
+            None => listing.children.push(path),
+        }
+    }
+    Ok(listing)
+}
+
+fn skip_reason(path: &Path, rules: &IgnoreRules, filter: &PathFilter) -> Option<SkipReason> {
+    if path.is_symlink() {
+        return Some(SkipReason::Symlink);
+    }
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return Some(SkipReason::Hidden);
+    }
+    let is_dir = path.is_dir();
+    if rules.is_ignored(path, is_dir) {
+        return Some(SkipReason::Ignored);
+    }
+    if !filter.allows(path, is_dir) {
+        return Some(SkipReason::Excluded);
+    }
+    None
+}
+
+pub fn find_git_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|p| p.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+fn load_git_exclude(git_root: &Path) -> Option<Arc<Gitignore>> {
+    let exclude_path = git_root.join(".git").join("info").join("exclude");
+    if !exclude_path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(git_root);
+    builder.add(&exclude_path);
+    builder.build().ok().map(Arc::new)
+}
+
+fn global_gitignore() -> &'static Gitignore {
+    static GLOBAL: OnceLock<Gitignore> = OnceLock::new();
+    GLOBAL.get_or_init(|| Gitignore::global().0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn names(paths: &[PathBuf]) -> Vec<String> {
+        let mut names: Vec<String> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn ignored_hidden_and_excluded_entries_are_left_out() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::write(root.join(".git/info/exclude"), "secret.txt\n").unwrap();
+        fs::write(root.join(".gitignore"), "*.log\ntarget/\n").unwrap();
+        // `.ignore` is checked before `.gitignore` in the same directory.
+        fs::write(root.join(".ignore"), "!keep.log\n").unwrap();
+        for file in ["main.rs", "debug.log", "keep.log", "secret.txt", ".env"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::create_dir(root.join("target")).unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+
+        let children = read_dir_filtered(root, &IgnoreRules::for_dir(root), &PathFilter::default());
+        assert_eq!(names(&children), ["keep.log", "main.rs", "src"]);
+    }
+
+    #[test]
+    fn ignore_files_above_the_root_do_not_apply_outside_a_repository() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.rs\n").unwrap();
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+        let rules = IgnoreRules::for_dir(&root);
+        assert!(!rules.is_ignored(&root.join("main.rs"), false));
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+    }
+
+    #[test]
+    fn ignore_files_up_to_the_repository_root_apply() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("crates").join("core");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.rs\n").unwrap();
+
+        let rules = IgnoreRules::for_dir(&root);
+        assert!(rules.is_ignored(&root.join("main.rs"), false));
+    }
+
+    #[test]
+    fn nested_repositories_do_not_inherit_rules() {
+        let dir = TempDir::new().unwrap();
+        let nested = dir.path().join("vendor");
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(nested.join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.c\n").unwrap();
+        fs::write(nested.join(".gitignore"), "*.o\n").unwrap();
+
+        let rules = IgnoreRules::for_dir(dir.path()).descend(&nested);
+        assert!(!rules.is_ignored(&nested.join("lib.c"), false));
+        assert!(rules.is_ignored(&nested.join("lib.o"), false));
+    }
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let include: Vec<String> = include.iter().map(|p| p.to_string()).collect();
+        let exclude: Vec<String> = exclude.iter().map(|p| p.to_string()).collect();
+        PathFilter::new(&include, &exclude)
+            .unwrap()
+            .with_root(Path::new("/repo"))
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_file_names_at_any_depth() {
+        let filter = filter(&["*.rs"], &[]);
+        assert!(filter.allows(Path::new("/repo/src/deep/lib.rs"), false));
+        assert!(!filter.allows(Path::new("/repo/README.md"), false));
+        assert!(filter.allows(Path::new("/repo/docs"), true));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_match_paths_from_the_root() {
+        let filter = filter(&["/src/**/*.rs"], &["src/generated/**"]);
+        assert!(filter.allows(Path::new("/repo/src/walk.rs"), false));
+        assert!(!filter.allows(Path::new("/repo/tests/walk.rs"), false));
+        assert!(!filter.allows(Path::new("/repo/src/generated/api.rs"), false));
+    }
+
+    #[test]
+    fn negated_patterns_move_to_the_opposite_list() {
+        let filter = filter(&["!*.lock"], &["!*.toml"]);
+        assert!(!filter.allows(Path::new("/repo/Cargo.lock"), false));
+        assert!(filter.allows(Path::new("/repo/Cargo.toml"), false));
+        assert!(!filter.allows(Path::new("/repo/main.rs"), false));
+    }
+
+    #[test]
+    fn trailing_slash_excludes_match_directories_only() {
+        let filter = filter(&[], &["docs/", "src/generated/"]);
+        assert!(!filter.allows(Path::new("/repo/docs"), true));
+        assert!(!filter.allows(Path::new("/repo/crates/core/docs"), true));
+        assert!(filter.allows(Path::new("/repo/docs"), false));
+        assert!(!filter.allows(Path::new("/repo/src/generated"), true));
+        assert!(filter.allows(Path::new("/repo/src/generated"), false));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!(PathFilter::new(&["src/[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn roots_are_the_deepest_shared_directory() {
+        let dir = TempDir::new().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(b.join("file.txt"), "").unwrap();
+
+        let paths = vec![
+            a.to_string_lossy().to_string(),
+            b.join("file.txt").to_string_lossy().to_string(),
+        ];
+        assert_eq!(resolve_roots(&paths), vec![dir.path().to_path_buf(); 2]);
+    }
+
+    #[test]
+    fn display_paths_are_relative_with_forward_slashes() {
+        let root = ParseRoot::new(Path::new("/work/app"), &PathFilter::default(), false);
+        assert_eq!(
+            root.display_path(Path::new("/work/app/src/main.rs")),
+            "src/main.rs"
+        );
+        assert_eq!(root.display_path(Path::new("/work/app")), "app");
+    }
+
+    #[test]
+    fn display_paths_can_be_prefixed_with_the_root_name() {
+        let root = ParseRoot::new(Path::new("/work/app"), &PathFilter::default(), true);
+        assert_eq!(
+            root.display_path(Path::new("/work/app/src/main.rs")),
+            "app/src/main.rs"
+        );
+        assert_eq!(root.display_path(Path::new("/work/app")), "app");
+    }
+
+    #[test]
+    fn sort_orders_compare_names_byte_wise() {
+        let mut entries = vec![
+            (PathBuf::from("/r/b.txt"), false),
+            (PathBuf::from("/r/src"), true),
+            (PathBuf::from("/r/B.txt"), false),
+            (PathBuf::from("/r/a"), true),
+        ];
+
+        SortOrder::DirectoriesFirst.sort(&mut entries);
+        assert_eq!(names_in_order(&entries), ["a", "src", "B.txt", "b.txt"]);
+
+        SortOrder::Lexical.sort(&mut entries);
+        assert_eq!(names_in_order(&entries), ["B.txt", "a", "b.txt", "src"]);
+    }
+
+    fn names_in_order(entries: &[(PathBuf, bool)]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+}
@@ -1,25 +1,38 @@
 import { z } from 'zod';
 
+// scp-like SSH address, e.g. `git@github.com:user/repo.git`.
+const SCP_URL = /^[\w.-]+@[\w.-]+:(?!\/\/)(.+)$/;
+
+/** Path segments of an HTTPS, SSH or scp-like repository URL, or `null` for anything else. */
+const repoPathSegments = (url: string): string[] | null => {
+  const scp = SCP_URL.exec(url);
+  if (scp) {
+    return scp[1].split('/').filter(Boolean);
+  }
+  try {
+    const { protocol, pathname } = new URL(url);
+    if (protocol !== 'https:' && protocol !== 'ssh:') {
+      return null;
+    }
+    return pathname.split('/').filter(Boolean);
+  } catch {
+    return null;
+  }
+};
+
 export const gitRepoSchema = z
   .string()
   .trim()
   .min(1, 'URL cannot be empty')
-  .url({ message: 'Invalid URL format' })
-  .startsWith('https://', 'URL must start with https://')
+  .refine(
+    (url) => repoPathSegments(url) !== null,
+    'URL must start with https:// or ssh://, or be an SSH address like git@host:user/repo'
+  )
   .refine((url) => {
-    try {
-      const { pathname } = new URL(url);
-      const segments = pathname.split('/').filter(Boolean);
-      return segments.length >= 2;
-    } catch {
-      return false;
-    }
+    const segments = repoPathSegments(url);
+    return segments === null || segments.length >= 2;
   }, 'Incomplete repository path. Example: https://github.com/user/repo')
 
-  .refine(
-    (url) => !/\/blob\/|\/tree\/|\/commit\//.test(url),
-    'Please provide the root repository URL (remove /tree/, /blob/, etc.)'
-  )
   .refine((url) => !/\.(zip|tar|gz|rar|7z)$/i.test(url), 'Cannot clone archive files directly');
 
 export const renameSchema = z
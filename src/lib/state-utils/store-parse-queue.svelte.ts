@@ -1,10 +1,19 @@
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { toast } from 'svelte-sonner';
 
+export type ParsePhase = 'counting' | 'writing' | 'tree' | 'finalizing';
+
 export type ParseProgress = {
   parse_id: string;
+  phase: ParsePhase;
   parse_progress: number;
   files_amount: number;
+  files_done: number;
+  bytes_done: number;
+  bytes_total: number;
+  current_file: string | null;
+  elapsed_ms: number;
+  eta_ms: number | null;
   result_file_path: string | null;
 };
 class ParseQueue {
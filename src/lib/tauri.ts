@@ -2,12 +2,15 @@ import { invoke } from '@tauri-apps/api/core';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { setSelectedRecursive } from '@/lib/utils/utils';
 
-import type { File, FileTree, FileMetadata } from '@/lib/type.ts';
+import type { File, FileTree, FileMetadata, PreviewOptions } from '@/lib/type.ts';
 
 type FileWithId = { id: string };
 
-export const getPreviewTreeNodes = async (paths: string[]): Promise<FileTree[]> => {
-  const nodes = await invoke<FileTree[]>('get_preview_tree', { paths });
+export const getPreviewTreeNodes = async (
+  paths: string[],
+  options?: PreviewOptions
+): Promise<FileTree[]> => {
+  const nodes = await invoke<FileTree[]>('get_preview_tree', { paths, options: options ?? null });
   return nodes.map((node) => {
     const selectedNode = setSelectedRecursive(node);
     return {
@@ -17,9 +20,17 @@ export const getPreviewTreeNodes = async (paths: string[]): Promise<FileTree[]>
   });
 };
 
-export const expandNode = async (path: string): Promise<FileTree[]> => {
+export const expandNode = async (
+  path: string,
+  root?: string,
+  options?: PreviewOptions
+): Promise<FileTree[]> => {
   try {
-    const children = await invoke<FileTree[]>('expand_folder', { path });
+    const children = await invoke<FileTree[]>('expand_folder', {
+      path,
+      root: root ?? null,
+      options: options ?? null
+    });
 
     return children.map((n) => ({ ...n, isExpanded: false, selected: true }));
   } catch (e) {
@@ -96,6 +107,6 @@ export const getFileMetadata = async (dirName: string): Promise<FileMetadata | n
   return metadata;
 };
 
-export const parseGitRepo = async (repoUrl: string): Promise<string> => {
-  return await invoke<string>('parse_repository', { url: repoUrl });
+export const parseGitRepo = async (repoUrl: string, gitRef?: string): Promise<string> => {
+  return await invoke<string>('parse_repository', { url: repoUrl, gitRef: gitRef ?? null });
 };
@@ -56,3 +56,20 @@ export type GraphData = {
   largeFile?: boolean;
   loading?: boolean;
 };
+
+export type ClonePhase = 'receiving' | 'resolving' | 'checking_out';
+
+export type CloneProgress = {
+  url: string;
+  phase: ClonePhase;
+  done: number;
+  total: number;
+  received_bytes: number;
+};
+
+/** The part of the parse options that decides which files a preview shows, and in what order. */
+export type PreviewOptions = {
+  include?: string[];
+  exclude?: string[];
+  sort_order?: 'directories_first' | 'lexical';
+};